@@ -1,6 +1,9 @@
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use rusqlite::Connection;
+use log::warn;
+use rusqlite::{Connection, OptionalExtension, params};
 use crate::erreur::Result;
+use crate::sessions;
 
 pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
     let conn = match fichier {
@@ -10,10 +13,38 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
 
     conn.execute_batch("
         BEGIN;
+        CREATE TABLE IF NOT EXISTS paramètre (
+            clé TEXT PRIMARY KEY,
+            valeur TEXT
+        );
+        CREATE TABLE IF NOT EXISTS année_scolaire (
+            id INTEGER PRIMARY KEY,
+            nom TEXT NOT NULL,
+            début TEXT NOT NULL,
+            fin TEXT NOT NULL,
+
+            CONSTRAINT u_nom UNIQUE (nom)
+        );
+        CREATE TABLE IF NOT EXISTS session (
+            id INTEGER PRIMARY KEY,
+            id_année INTEGER NOT NULL,
+            semestre INTEGER NOT NULL,
+            début TEXT,
+            fin TEXT,
+
+            CONSTRAINT f_année FOREIGN KEY (id_année) REFERENCES année_scolaire(id),
+            CONSTRAINT u_semestre UNIQUE (id_année, semestre),
+            CONSTRAINT c_semestre CHECK (semestre = 1 OR semestre = 2)
+        );
         CREATE TABLE IF NOT EXISTS cours (
             id INTEGER PRIMARY KEY,
             code TEXT NOT NULL,
-            nom TEXT
+            nom TEXT,
+            id_session INTEGER,
+            id_groupe INTEGER,
+
+            CONSTRAINT u_code UNIQUE (code, id_session),
+            CONSTRAINT f_session FOREIGN KEY (id_session) REFERENCES session(id)
         );
         CREATE TABLE IF NOT EXISTS étiquette (
             id INTEGER PRIMARY KEY,
@@ -21,25 +52,53 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
 
             CONSTRAINT u_nom UNIQUE (nom)
         );
-        INSERT OR IGNORE INTO étiquette(nom) VALUES
-            ('Virtuel'),
-            ('AP');
         CREATE TABLE IF NOT EXISTS élève (
             id INTEGER PRIMARY KEY,
+            id_encompass INTEGER,
             prénom_préféré TEXT,
             prénom TEXT NOT NULL,
             nom TEXT NOT NULL,
+            naissance TEXT,
+            majeur_encompass INTEGER NOT NULL DEFAULT 0,
+            majeur_manuel INTEGER,
+            niveau INTEGER,
+            crédits REAL,
+            pei INTEGER NOT NULL DEFAULT 0,
+            alf INTEGER NOT NULL DEFAULT 0,
+            courriel TEXT,
+            niso TEXT,
+            foyer TEXT,
+            conseiller TEXT,
+
+            CONSTRAINT u_encompass UNIQUE (id_encompass),
+            CONSTRAINT c_majeur_encompass CHECK (majeur_encompass = 0 OR majeur_encompass = 1),
+            CONSTRAINT c_majeur_manuel CHECK (majeur_manuel = 0 OR majeur_manuel = 1),
+            CONSTRAINT c_pei CHECK (pei = 0 OR pei = 1),
+            CONSTRAINT c_alf CHECK (alf = 0 OR alf = 1)
+        );
+        CREATE TABLE IF NOT EXISTS élève_indicateur (
+            id_élève INTEGER NOT NULL,
+            nom TEXT NOT NULL,
+            valeur TEXT NOT NULL,
+
+            CONSTRAINT u_indicateur UNIQUE (id_élève, nom),
+            CONSTRAINT f_élève FOREIGN KEY (id_élève) REFERENCES élève(id)
+        );
+        CREATE TABLE IF NOT EXISTS inscription (
+            id_élève INTEGER NOT NULL,
             id_cours INTEGER NOT NULL,
 
+            CONSTRAINT f_élève FOREIGN KEY (id_élève) REFERENCES élève(id),
             CONSTRAINT f_cours FOREIGN KEY (id_cours) REFERENCES cours(id),
-            CONSTRAINT u_élève UNIQUE (prénom, nom, id_cours)
+            CONSTRAINT u_inscription UNIQUE (id_élève, id_cours)
         );
         CREATE TABLE IF NOT EXISTS élève_étiquette (
             id_élève INTEGER NOT NULL,
             id_étiquette INTEGER NOT NULL,
 
             CONSTRAINT f_élève FOREIGN KEY (id_élève) REFERENCES élève(id),
-            CONSTRAINT f_étiquette FOREIGN KEY (id_étiquette) REFERENCES étiquette(id)
+            CONSTRAINT f_étiquette FOREIGN KEY (id_étiquette) REFERENCES étiquette(id),
+            CONSTRAINT u_étiquette UNIQUE (id_élève, id_étiquette)
         );
         CREATE TABLE IF NOT EXISTS élève_contact (
             id INTEGER PRIMARY KEY,
@@ -49,10 +108,14 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
             correspondance INTEGER NOT NULL,
             automatique INTEGER NOT NULL,
             ordre INTEGER,
+            consentement INTEGER NOT NULL DEFAULT 0,
+            consentement_manuel INTEGER,
 
             CONSTRAINT u_nom UNIQUE (id_élève, nom_complet),
             CONSTRAINT c_correspondance CHECK (correspondance = 0 OR correspondance = 1),
-            CONSTRAINT c_automatique CHECK (automatique = 0 OR automatique = 1)
+            CONSTRAINT c_automatique CHECK (automatique = 0 OR automatique = 1),
+            CONSTRAINT c_consentement CHECK (consentement = 0 OR consentement = 1),
+            CONSTRAINT c_consentement_manuel CHECK (consentement_manuel = 0 OR consentement_manuel = 1)
         );
         CREATE TABLE IF NOT EXISTS élève_contact_type (
             id INTEGER PRIMARY KEY,
@@ -70,11 +133,26 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
             id_type INTEGER NOT NULL,
             coordonnée TEXT NOT NULL,
             automatique INTEGER NOT NULL,
+            normalisée TEXT,
+            valide INTEGER,
 
             CONSTRAINT f_contact FOREIGN KEY (id_contact) REFERENCES élève_contact(id),
             CONSTRAINT f_type FOREIGN KEY (id_type) REFERENCES élève_contact_type(id),
             CONSTRAINT u_coordonnée UNIQUE (id_contact, coordonnée, automatique),
-            CONSTRAINT c_automatique CHECK (automatique = 0 OR automatique = 1)
+            CONSTRAINT c_automatique CHECK (automatique = 0 OR automatique = 1),
+            CONSTRAINT c_valide CHECK (valide = 0 OR valide = 1)
+        );
+        CREATE TABLE IF NOT EXISTS tuteur (
+            id INTEGER PRIMARY KEY,
+            nom_complet TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS tuteur_contact (
+            id_tuteur INTEGER NOT NULL,
+            id_contact INTEGER NOT NULL,
+
+            CONSTRAINT f_tuteur FOREIGN KEY (id_tuteur) REFERENCES tuteur(id),
+            CONSTRAINT f_contact FOREIGN KEY (id_contact) REFERENCES élève_contact(id),
+            CONSTRAINT u_contact UNIQUE (id_contact)
         );
         CREATE TABLE IF NOT EXISTS échelle_niveau (
             id_échelle INTEGER NOT NULL,
@@ -102,6 +180,7 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
             indice INTEGER NOT NULL,
             id_échelle INTEGER,
             formule TEXT,
+            pondération REAL NOT NULL DEFAULT 1,
 
             CONSTRAINT u_position UNIQUE (id_cours, id_parent, indice),
             CONSTRAINT c_type CHECK (id_parent IS NULL OR id_échelle IS NULL),
@@ -109,7 +188,8 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
             CONSTRAINT f_cours FOREIGN KEY (id_cours) REFERENCES cours(id),
             CONSTRAINT f_parent FOREIGN KEY (id_parent) REFERENCES évaluation_item(id),
             CONSTRAINT c_indice CHECK (indice >= 0),
-            CONSTRAINT f_échelle FOREIGN KEY (id_échelle) REFERENCES échelle(id)
+            CONSTRAINT f_échelle FOREIGN KEY (id_échelle) REFERENCES échelle(id),
+            CONSTRAINT c_pondération CHECK (pondération >= 0)
         );
         CREATE TABLE IF NOT EXISTS évaluation_reprise (
             id INTEGER PRIMARY KEY,
@@ -131,6 +211,28 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
             CONSTRAINT f_reprise FOREIGN KEY (id_reprise) REFERENCES évaluation_reprise(id),
             CONSTRAINT f_élève FOREIGN KEY (id_élève) REFERENCES élève(id)
         );
+        CREATE TABLE IF NOT EXISTS assiduité (
+            id_élève INTEGER NOT NULL,
+            id_cours INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            période INTEGER NOT NULL,
+            retard INTEGER NOT NULL DEFAULT 0,
+
+            CONSTRAINT u_période UNIQUE (id_élève, id_cours, date, période),
+            CONSTRAINT c_retard CHECK (retard = 0 OR retard = 1),
+            CONSTRAINT f_élève FOREIGN KEY (id_élève) REFERENCES élève(id),
+            CONSTRAINT f_cours FOREIGN KEY (id_cours) REFERENCES cours(id)
+        );
+        CREATE TABLE IF NOT EXISTS élève_appel (
+            id INTEGER PRIMARY KEY,
+            id_élève INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            réussi INTEGER NOT NULL,
+            note TEXT,
+
+            CONSTRAINT f_élève FOREIGN KEY (id_élève) REFERENCES élève(id),
+            CONSTRAINT c_réussi CHECK (réussi = 0 OR réussi = 1)
+        );
         COMMIT;
     "
     /*
@@ -203,5 +305,460 @@ pub fn ouvrir(fichier: Option<&str>) -> Result<Connection> {
     "*/
     )?;
 
+    ajouter_colonne(&conn, "élève_contact_item", "normalisée", "TEXT")?;
+    ajouter_colonne(&conn, "élève_contact_item", "valide", "INTEGER CHECK (valide = 0 OR valide = 1)")?;
+    ajouter_colonne(&conn, "évaluation_item", "pondération", "REAL NOT NULL DEFAULT 1 CHECK (pondération >= 0)")?;
+    ajouter_colonne(&conn, "cours", "id_session", "INTEGER REFERENCES session(id)")?;
+    ajouter_colonne(&conn, "cours", "id_groupe", "INTEGER")?;
+    ajouter_colonne(&conn, "élève", "naissance", "TEXT")?;
+    ajouter_colonne(&conn, "élève", "majeur_encompass", "INTEGER NOT NULL DEFAULT 0 CHECK (majeur_encompass = 0 OR majeur_encompass = 1)")?;
+    ajouter_colonne(&conn, "élève", "majeur_manuel", "INTEGER CHECK (majeur_manuel = 0 OR majeur_manuel = 1)")?;
+    ajouter_colonne(&conn, "élève", "niveau", "INTEGER")?;
+    ajouter_colonne(&conn, "élève", "crédits", "REAL")?;
+    ajouter_colonne(&conn, "élève", "pei", "INTEGER NOT NULL DEFAULT 0 CHECK (pei = 0 OR pei = 1)")?;
+    ajouter_colonne(&conn, "élève", "alf", "INTEGER NOT NULL DEFAULT 0 CHECK (alf = 0 OR alf = 1)")?;
+    ajouter_colonne(&conn, "élève", "courriel", "TEXT")?;
+    ajouter_colonne(&conn, "élève", "niso", "TEXT")?;
+    ajouter_colonne(&conn, "élève", "foyer", "TEXT")?;
+    ajouter_colonne(&conn, "élève", "conseiller", "TEXT")?;
+    ajouter_colonne(&conn, "élève_contact", "consentement", "INTEGER NOT NULL DEFAULT 0 CHECK (consentement = 0 OR consentement = 1)")?;
+    ajouter_colonne(&conn, "élève_contact", "consentement_manuel", "INTEGER CHECK (consentement_manuel = 0 OR consentement_manuel = 1)")?;
+
+    séparer_inscriptions(&conn)?;
+    dédoubler_étiquettes(&conn)?;
+    créer_niveaux(&conn)?;
+
+    // Les cours importés avant l'ajout des sessions sont rattachés à la session courante, qui n'est créée qu'au besoin.
+    let sans_session: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM cours WHERE id_session IS NULL);", [], |r| r.get(0))?;
+    if sans_session {
+        let id_session = sessions::session_courante(&conn)?;
+        conn.execute("UPDATE cours SET id_session = ?1 WHERE id_session IS NULL;", [id_session])?;
+    }
+    unifier_cours(&conn)?;
+
+    // Un élève majeur ne peut être joint que par les contacts auxquels il a consenti. Les choix manuels ont priorité
+    // sur EnCompass et sur la date de naissance.
+    conn.execute_batch("
+        BEGIN;
+        DROP VIEW IF EXISTS élève_contact_item_effectif;
+        CREATE VIEW élève_contact_item_effectif AS
+            SELECT i.*
+                FROM élève_contact_item AS i
+                WHERE i.automatique = 0 OR NOT EXISTS (
+                    SELECT 1
+                        FROM élève_contact_item AS m
+                        WHERE m.id_contact = i.id_contact AND m.id_type = i.id_type AND m.automatique = 0
+                );
+        DROP VIEW IF EXISTS élève_contact_autorisé;
+        DROP VIEW IF EXISTS élève_statut;
+        CREATE VIEW élève_statut AS
+            SELECT id AS id_élève,
+                COALESCE(majeur_manuel, majeur_encompass = 1 OR COALESCE(DATE(naissance, '+18 years') <= DATE('now', 'localtime'), 0)) AS majeur
+                FROM élève;
+        CREATE VIEW élève_contact_autorisé AS
+            SELECT c.*
+                FROM élève_contact AS c
+                JOIN élève_statut AS s ON s.id_élève = c.id_élève
+                WHERE c.correspondance = 1 AND (s.majeur = 0 OR COALESCE(c.consentement_manuel, c.consentement) = 1);
+        DROP VIEW IF EXISTS élève_assiduité;
+        CREATE VIEW élève_assiduité AS
+            SELECT i.id_élève, i.id_cours,
+                COALESCE(SUM(a.retard = 0), 0) AS absences,
+                COUNT(DISTINCT CASE WHEN a.retard = 0 THEN a.date END) AS jours_absence,
+                COALESCE(SUM(a.retard = 1), 0) AS retards,
+                MAX(CASE WHEN a.retard = 0 THEN a.date END) AS dernière_absence
+                FROM inscription AS i
+                LEFT JOIN assiduité AS a ON a.id_élève = i.id_élève AND a.id_cours = i.id_cours
+                GROUP BY i.id_élève, i.id_cours;
+        DROP VIEW IF EXISTS élève_étiquette_nom;
+        CREATE VIEW élève_étiquette_nom AS
+            SELECT ée.id_élève, étiquette.nom
+                FROM élève_étiquette AS ée
+                JOIN étiquette ON étiquette.id = ée.id_étiquette
+            UNION
+            SELECT id, 'EnCompass:PEI' FROM élève WHERE pei = 1
+            UNION
+            SELECT id, 'EnCompass:ALF' FROM élève WHERE alf = 1
+            UNION
+            SELECT id, 'EnCompass:Niveau ' || niveau FROM élève WHERE niveau IS NOT NULL;
+        COMMIT;
+    ")?;
+
     Ok(conn)
 }
+
+// Les niveaux de rendement de l'Ontario pour les échelles créées lors des importations, si elles n'en ont pas déjà. Un
+// résultat à la limite de deux niveaux compte pour le plus élevé.
+pub fn créer_niveaux(conn: &Connection) -> Result<()> {
+    conn.execute_batch("
+        INSERT INTO échelle_niveau(id_échelle, nom, min, max)
+            SELECT échelle.id, n.column1, n.column2, n.column3
+                FROM échelle
+                JOIN (VALUES ('R', 0, 1), ('1', 1, 2), ('2', 2, 3), ('3', 3, 4), ('4', 4, 4)) AS n
+                WHERE échelle.nom = 'Niveau' AND échelle.min = 0 AND échelle.max = 4
+                    AND NOT EXISTS (SELECT 1 FROM échelle_niveau WHERE id_échelle = échelle.id);
+        INSERT INTO échelle_niveau(id_échelle, nom, min, max)
+            SELECT échelle.id, n.column1, n.column2, n.column3
+                FROM échelle
+                JOIN (VALUES ('R', 0, 50), ('1', 50, 60), ('2', 60, 70), ('3', 70, 80), ('4', 80, 100)) AS n
+                WHERE échelle.nom = 'Pourcentage' AND échelle.min = 0 AND échelle.max = 100
+                    AND NOT EXISTS (SELECT 1 FROM échelle_niveau WHERE id_échelle = échelle.id);
+    ")?;
+
+    Ok(())
+}
+
+pub fn lire_paramètre(conn: &Connection, clé: &str) -> Result<Option<String>> {
+    Ok(conn.query_row("SELECT valeur FROM paramètre WHERE clé = ?1", [clé], |r| r.get(0)).optional()?)
+}
+
+// Sans valeur, le paramètre est supprimé.
+pub fn écrire_paramètre(conn: &Connection, clé: &str, valeur: Option<&str>) -> Result<()> {
+    match valeur {
+        Some(valeur) => conn.execute("
+            INSERT INTO paramètre(clé, valeur) VALUES (?1, ?2)
+                ON CONFLICT(clé) DO UPDATE SET valeur = excluded.valeur;
+        ", [clé, valeur])?,
+        None => conn.execute("DELETE FROM paramètre WHERE clé = ?1", [clé])?
+    };
+
+    Ok(())
+}
+
+fn colonne_existe(conn: &Connection, table: &str, colonne: &str) -> Result<bool> {
+    Ok(conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |r| r.get::<_, String>(1))?
+        .filter_map(|c| c.ok())
+        .any(|c| c == colonne))
+}
+
+fn ajouter_colonne(conn: &Connection, table: &str, colonne: &str, définition: &str) -> Result<()> {
+    if !colonne_existe(conn, table, colonne)? {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {};", table, colonne, définition))?;
+    }
+
+    Ok(())
+}
+
+// La table élève_étiquette n'avait pas de contrainte d'unicité. Les doublons sont retirés avant d'en ajouter une.
+fn dédoubler_étiquettes(conn: &Connection) -> Result<()> {
+    let unique: bool = conn.query_row("
+        SELECT EXISTS (SELECT 1 FROM pragma_index_list('élève_étiquette') WHERE \"unique\" = 1);
+    ", [], |r| r.get(0))?;
+    if unique {
+        return Ok(());
+    }
+
+    conn.execute_batch("
+        BEGIN;
+        DELETE FROM élève_étiquette
+            WHERE rowid NOT IN (SELECT MIN(rowid) FROM élève_étiquette GROUP BY id_élève, id_étiquette);
+        CREATE UNIQUE INDEX u_élève_étiquette ON élève_étiquette(id_élève, id_étiquette);
+        COMMIT;
+    ")?;
+
+    Ok(())
+}
+
+// Un code de cours est unique dans sa session. Les cours en double d'une base de données existante ne sont pas fusionnés
+// automatiquement, puisque leurs évaluations et leurs inscriptions pourraient différer; ils sont signalés et la contrainte
+// n'est ajoutée qu'une fois les doublons retirés.
+fn unifier_cours(conn: &Connection) -> Result<()> {
+    let unique: bool = conn.query_row("
+        SELECT EXISTS (SELECT 1 FROM pragma_index_list('cours') WHERE \"unique\" = 1);
+    ", [], |r| r.get(0))?;
+    if unique {
+        return Ok(());
+    }
+
+    let mut stmt = conn.prepare("
+        SELECT code, GROUP_CONCAT(id, ', ')
+            FROM cours
+            WHERE id_session IS NOT NULL
+            GROUP BY code, id_session
+            HAVING COUNT(*) > 1;
+    ")?;
+    let doublons: Vec<(String, String)> = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    if !doublons.is_empty() {
+        for (code, ids) in doublons {
+            warn!("Le cours {} est en double dans sa session (identifiants {}); il doit être fusionné manuellement.", code, ids);
+        }
+        return Ok(());
+    }
+
+    conn.execute("CREATE UNIQUE INDEX u_cours_code ON cours(code, id_session);", [])?;
+
+    Ok(())
+}
+
+// Les élèves étaient auparavant dupliqués dans chacun de leurs cours. Sans identifiant EnCompass, les élèves de même nom
+// qui ont au moins un contact ou une coordonnée en commun sont considérés comme une seule personne, qui reçoit les
+// contacts, les étiquettes et les résultats de ses doublons. Les autres sont conservés séparément et signalés.
+fn séparer_inscriptions(conn: &Connection) -> Result<()> {
+    if !colonne_existe(conn, "élève", "id_cours")? {
+        return Ok(());
+    }
+
+    // Les clés étrangères ne peuvent pas être désactivées pendant une transaction; elles sont rétablies même en cas
+    // d'erreur, après l'annulation de la transaction.
+    let clés_étrangères: bool = conn.query_row("PRAGMA foreign_keys;", [], |r| r.get(0))?;
+    conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+    let résultat = fusionner_élèves(conn);
+    conn.execute_batch(if clés_étrangères { "PRAGMA foreign_keys = ON;" } else { "PRAGMA foreign_keys = OFF;" })?;
+
+    résultat
+}
+
+// Retourne l'élève canonique de chaque élève, soit le premier de ceux de même nom dont les contacts se recoupent.
+fn regrouper_doublons(conn: &Connection) -> Result<Vec<(i64, i64)>> {
+    let mut stmt = conn.prepare("
+        SELECT é.id, é.prénom, é.nom, c.nom_complet, i.coordonnée
+            FROM élève AS é
+            LEFT JOIN élève_contact AS c ON c.id_élève = é.id
+            LEFT JOIN élève_contact_item AS i ON i.id_contact = c.id
+            ORDER BY é.id;
+    ")?;
+    let mut rangées = stmt.query([])?;
+
+    let mut élèves: Vec<(i64, (String, String), HashSet<String>)> = Vec::new();
+    while let Some(r) = rangées.next()? {
+        let id: i64 = r.get(0)?;
+        if élèves.last().map(|é| é.0) != Some(id) {
+            élèves.push((id, (r.get(1)?, r.get(2)?), HashSet::new()));
+        }
+        let clés = &mut élèves.last_mut().unwrap().2;
+        if let Some(nom_complet) = r.get::<_, Option<String>>(3)? {
+            clés.insert(format!("contact:{}", nom_complet.trim().to_lowercase()));
+        }
+        if let Some(coordonnée) = r.get::<_, Option<String>>(4)? {
+            clés.insert(format!("coordonnée:{}", coordonnée.trim().to_lowercase()));
+        }
+    }
+
+    // Chaque groupe réunit les élèves de même nom dont les contacts se recoupent, directement ou par un autre élève: l'élève
+    // canonique, les élèves du groupe et leurs contacts.
+    type Groupe = (i64, Vec<i64>, HashSet<String>);
+    let mut groupes: HashMap<(String, String), Vec<Groupe>> = HashMap::new();
+    for (id, nom, clés) in élèves {
+        let groupes_nom = groupes.entry(nom).or_default();
+        let (mut ids, mut clés_groupe) = (vec![id], clés);
+        let mut i = 0;
+        while i < groupes_nom.len() {
+            if groupes_nom[i].2.is_disjoint(&clés_groupe) {
+                i += 1;
+            } else {
+                let (_, autres, autres_clés) = groupes_nom.remove(i);
+                ids.extend(autres);
+                clés_groupe.extend(autres_clés);
+            }
+        }
+        ids.sort_unstable();
+        groupes_nom.push((ids[0], ids, clés_groupe));
+    }
+
+    let mut fusion = Vec::new();
+    for ((prénom, nom), groupes_nom) in groupes {
+        if groupes_nom.len() > 1 {
+            warn!(
+                "{} élèves nommés {} {} n'ont aucun contact en commun; ils sont conservés séparément et pourraient être la même personne.",
+                groupes_nom.len(), prénom, nom
+            );
+        }
+        for (id_canonique, ids, _) in groupes_nom {
+            fusion.extend(ids.into_iter().map(|id| (id, id_canonique)));
+        }
+    }
+
+    Ok(fusion)
+}
+
+fn fusionner_élèves(conn: &Connection) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+
+    tx.execute_batch("CREATE TEMP TABLE fusion (id INTEGER PRIMARY KEY, id_canonique INTEGER NOT NULL);")?;
+    for (id, id_canonique) in regrouper_doublons(&tx)? {
+        tx.execute("INSERT INTO fusion(id, id_canonique) VALUES (?1, ?2);", params![id, id_canonique])?;
+    }
+
+    tx.execute_batch("
+
+        INSERT OR IGNORE INTO inscription(id_élève, id_cours)
+            SELECT fusion.id_canonique, élève.id_cours
+                FROM élève
+                JOIN fusion ON fusion.id = élève.id;
+
+        UPDATE OR IGNORE élève_contact
+            SET id_élève = (SELECT id_canonique FROM fusion WHERE fusion.id = élève_contact.id_élève);
+        CREATE TEMP TABLE contact_doublon AS
+            SELECT c.id AS id, canonique.id AS id_canonique
+                FROM élève_contact AS c
+                JOIN fusion ON fusion.id = c.id_élève
+                JOIN élève_contact AS canonique ON canonique.id_élève = fusion.id_canonique AND canonique.nom_complet = c.nom_complet
+                WHERE fusion.id <> fusion.id_canonique;
+        UPDATE OR IGNORE élève_contact_item
+            SET id_contact = (SELECT id_canonique FROM contact_doublon WHERE contact_doublon.id = élève_contact_item.id_contact)
+            WHERE id_contact IN (SELECT id FROM contact_doublon);
+        DELETE FROM élève_contact_item WHERE id_contact IN (SELECT id FROM contact_doublon);
+        DELETE FROM tuteur_contact WHERE id_contact IN (SELECT id FROM contact_doublon);
+        DELETE FROM élève_contact WHERE id IN (SELECT id FROM contact_doublon);
+
+        UPDATE élève_étiquette
+            SET id_élève = (SELECT id_canonique FROM fusion WHERE fusion.id = élève_étiquette.id_élève);
+        UPDATE OR IGNORE évaluation_résultat
+            SET id_élève = (SELECT id_canonique FROM fusion WHERE fusion.id = évaluation_résultat.id_élève);
+
+        CREATE TABLE élève_personne (
+            id INTEGER PRIMARY KEY,
+            id_encompass INTEGER,
+            prénom_préféré TEXT,
+            prénom TEXT NOT NULL,
+            nom TEXT NOT NULL,
+            naissance TEXT,
+            majeur_encompass INTEGER NOT NULL DEFAULT 0,
+            majeur_manuel INTEGER,
+            niveau INTEGER,
+            crédits REAL,
+            pei INTEGER NOT NULL DEFAULT 0,
+            alf INTEGER NOT NULL DEFAULT 0,
+            courriel TEXT,
+            niso TEXT,
+            foyer TEXT,
+            conseiller TEXT,
+
+            CONSTRAINT u_encompass UNIQUE (id_encompass),
+            CONSTRAINT c_majeur_encompass CHECK (majeur_encompass = 0 OR majeur_encompass = 1),
+            CONSTRAINT c_majeur_manuel CHECK (majeur_manuel = 0 OR majeur_manuel = 1),
+            CONSTRAINT c_pei CHECK (pei = 0 OR pei = 1),
+            CONSTRAINT c_alf CHECK (alf = 0 OR alf = 1)
+        );
+        INSERT INTO élève_personne(id, prénom_préféré, prénom, nom)
+            SELECT id, prénom_préféré, prénom, nom
+                FROM élève
+                WHERE id IN (SELECT id_canonique FROM fusion);
+        DROP TABLE élève;
+        ALTER TABLE élève_personne RENAME TO élève;
+
+        DROP TABLE fusion;
+        DROP TABLE contact_doublon;
+    ")?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Une base de données d'avant les inscriptions, où l'élève était dupliqué dans chacun de ses cours.
+    #[test]
+    fn fusion_élèves() {
+        let fichier = std::env::temp_dir().join(format!("contacteur_fusion_{}.db3", std::process::id()));
+        let _ = std::fs::remove_file(&fichier);
+        {
+            let conn = Connection::open(&fichier).unwrap();
+            conn.execute_batch("
+                CREATE TABLE cours (id INTEGER PRIMARY KEY, code TEXT NOT NULL, nom TEXT);
+                CREATE TABLE élève (id INTEGER PRIMARY KEY, id_cours INTEGER, prénom_préféré TEXT, prénom TEXT NOT NULL, nom TEXT NOT NULL);
+                CREATE TABLE élève_contact (
+                    id INTEGER PRIMARY KEY, id_élève INTEGER NOT NULL, nom_complet TEXT NOT NULL, relation TEXT,
+                    correspondance INTEGER NOT NULL, automatique INTEGER NOT NULL, ordre INTEGER,
+                    CONSTRAINT u_nom UNIQUE (id_élève, nom_complet)
+                );
+                CREATE TABLE élève_contact_item (
+                    id_contact INTEGER NOT NULL, id_type INTEGER NOT NULL, coordonnée TEXT NOT NULL, automatique INTEGER NOT NULL,
+                    CONSTRAINT u_coordonnée UNIQUE (id_contact, coordonnée, automatique)
+                );
+                INSERT INTO cours(id, code) VALUES (1, 'MHF4U-01'), (2, 'MCV4U-01'), (3, 'ENG4U-01');
+                INSERT INTO élève(id, id_cours, prénom, nom) VALUES
+                    (1, 1, 'Léa', 'Tremblay'), (2, 2, 'Léa', 'Tremblay'), (3, 3, 'Léa', 'Tremblay'), (4, 1, 'Noah', 'Gagnon'),
+                    (5, 2, 'Noah', 'Gagnon'), (6, 3, 'Emma', 'Roy'), (7, 1, 'Emma', 'Roy');
+                INSERT INTO élève_contact(id, id_élève, nom_complet, correspondance, automatique) VALUES
+                    (1, 1, 'Marie Tremblay', 1, 1), (2, 2, 'Marie Tremblay', 1, 1), (3, 3, 'Julie Côté', 1, 1),
+                    (4, 6, 'Paul Roy', 1, 1), (5, 7, 'P. Roy', 1, 1);
+                INSERT INTO élève_contact_item(id_contact, id_type, coordonnée, automatique) VALUES
+                    (1, 1, 'marie@exemple.ca', 1), (2, 1, 'marie@exemple.ca', 1), (3, 1, 'julie@exemple.ca', 1),
+                    (4, 2, '613-555-1234', 1), (5, 2, '613-555-1234', 1);
+            ").unwrap();
+        }
+
+        let conn = ouvrir(Some(fichier.to_str().unwrap())).unwrap();
+        let inscriptions: Vec<(i64, i64)> = conn
+            .prepare("SELECT id_élève, id_cours FROM inscription ORDER BY id_élève, id_cours;").unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        let contacts: Vec<(i64, String)> = conn
+            .prepare("SELECT id_élève, nom_complet FROM élève_contact ORDER BY id_élève, nom_complet;").unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        let clés_étrangères: bool = conn.query_row("PRAGMA foreign_keys;", [], |r| r.get(0)).unwrap();
+        drop(conn);
+        std::fs::remove_file(&fichier).unwrap();
+
+        // Léa (3) n'a aucun contact en commun avec les deux autres et Noah n'a aucun contact; Emma partage un téléphone.
+        assert_eq!(inscriptions, vec![(1, 1), (1, 2), (3, 3), (4, 1), (5, 2), (6, 1), (6, 3)]);
+        assert_eq!(contacts, vec![
+            (1, "Marie Tremblay".to_string()),
+            (3, "Julie Côté".to_string()),
+            (6, "P. Roy".to_string()),
+            (6, "Paul Roy".to_string())
+        ]);
+        let défaut: bool = Connection::open_in_memory().unwrap().query_row("PRAGMA foreign_keys;", [], |r| r.get(0)).unwrap();
+        assert_eq!(clés_étrangères, défaut);
+    }
+
+    #[test]
+    fn statut_majeur() {
+        let conn = ouvrir(None).unwrap();
+        conn.execute_batch("
+            INSERT INTO élève(id, prénom, nom, naissance, majeur_encompass, majeur_manuel) VALUES
+                (1, 'Léa', 'Tremblay', '2000-02-29', 0, NULL), (2, 'Noah', 'Gagnon', DATE('now', 'localtime', '-17 years'), 0, NULL),
+                (3, 'Emma', 'Roy', NULL, 1, NULL), (4, 'Liam', 'Côté', '2000-01-01', 1, 0), (5, 'Zoé', 'Roy', NULL, 0, NULL);
+        ").unwrap();
+        let majeurs: Vec<i64> = conn
+            .prepare("SELECT id_élève FROM élève_statut WHERE majeur ORDER BY id_élève;").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(majeurs, vec![1, 3]);
+
+        let anniversaire: String = conn.query_row("SELECT DATE('2000-02-29', '+18 years');", [], |r| r.get(0)).unwrap();
+        assert_eq!(anniversaire, "2018-03-01");
+    }
+
+    #[test]
+    fn ouverture_sans_session() {
+        let conn = ouvrir(None).unwrap();
+        let sessions: i64 = conn.query_row("SELECT COUNT(*) FROM session;", [], |r| r.get(0)).unwrap();
+        assert_eq!(sessions, 0);
+
+        conn.execute_batch("
+            INSERT INTO année_scolaire(id, nom, début, fin) VALUES (100, '2030-2031', '2030-09-01', '2031-06-30');
+            INSERT INTO session(id, id_année, semestre, début, fin) VALUES (100, 100, 1, '2030-09-01', '2031-01-31');
+            INSERT INTO cours(code, id_session) VALUES ('MHF4U-01', 100);
+        ").unwrap();
+        assert!(conn.execute("INSERT INTO cours(code, id_session) VALUES ('MHF4U-01', 100);", []).is_err());
+    }
+
+    #[test]
+    fn cours_en_double() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("
+            CREATE TABLE cours (id INTEGER PRIMARY KEY, code TEXT NOT NULL, nom TEXT, id_session INTEGER, id_groupe INTEGER);
+            INSERT INTO cours(id, code, id_session) VALUES (1, 'MHF4U-01', 1), (2, 'MHF4U-01', 1), (3, 'MHF4U-01', 2);
+        ").unwrap();
+        let unique = |conn: &Connection| -> bool {
+            conn.query_row("SELECT EXISTS (SELECT 1 FROM pragma_index_list('cours') WHERE \"unique\" = 1);", [], |r| r.get(0)).unwrap()
+        };
+
+        unifier_cours(&conn).unwrap();
+        assert!(!unique(&conn));
+
+        conn.execute("DELETE FROM cours WHERE id = 2;", []).unwrap();
+        unifier_cours(&conn).unwrap();
+        assert!(unique(&conn));
+        assert!(conn.execute("INSERT INTO cours(code, id_session) VALUES ('MHF4U-01', 2);", []).is_err());
+    }
+}
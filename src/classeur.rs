@@ -1,7 +1,9 @@
 use std::collections::HashMap;
+use std::sync::OnceLock;
 use regex::Regex;
-use spreadsheet_ods::{CellStyle, read_ods, Sheet, style::{FontFaceDecl, units::Length}, Value, WorkBook, write_ods, xmltree::XmlContent};
-use crate::erreur::Result;
+use spreadsheet_ods::{CellStyle, CellStyleRef, read_ods, style::{FontFaceDecl, units::Length}, Value, WorkBook, xmltree::XmlContent};
+use crate::erreur::{Contexte, Result};
+use crate::etiquettes::lire_étiquettes;
 
 const DÉCALAGE_NOTES: u32 = 5;
 
@@ -12,6 +14,8 @@ pub struct ClientClasseur {
 #[derive(Clone)]
 pub struct Cours {
     idx: usize,
+    colonne_encompass: Option<u32>,
+    pub feuille: String,
     pub code: String,
     pub évaluations: Vec<Évaluation>,
     pub élèves: Vec<Élève>
@@ -20,6 +24,7 @@ pub struct Cours {
 #[derive(Clone)]
 pub struct Élève {
     idx: u32,
+    pub id_encompass: Option<i64>,
     pub prénom: String,
     pub nom: String,
     pub prénom_préféré: String,
@@ -28,11 +33,32 @@ pub struct Élève {
 }
 
 impl Élève {
+    // Le numéro de la ligne tel qu'affiché dans le tableur.
+    pub fn ligne(&self) -> u32 {
+        self.idx + 1
+    }
+
     pub fn note(&self, composant: &ComposantÉvaluation) -> Option<f64> {
         self.notes[composant.idx as usize]
     }
 }
 
+#[derive(Clone)]
+pub struct ContactManuel {
+    pub cours: String,
+    pub prénom: String,
+    pub nom: String,
+    pub nom_complet: String,
+    pub relation: Option<String>,
+    pub correspondance: Option<bool>,
+    pub consentement: Option<bool>,
+    pub majeur: Option<bool>,
+    pub courriel: Option<String>,
+    pub tel_domicile: Option<String>,
+    pub tel_travail: Option<String>,
+    pub tel_cellulaire: Option<String>
+}
+
 #[derive(Clone)]
 pub struct ComposantÉvaluation {
     idx: u32,
@@ -42,23 +68,21 @@ pub struct ComposantÉvaluation {
 
 #[derive(Clone)]
 pub struct SectionÉvaluation {
-    idx: u32,
     pub nom: String,
     pub composants: Vec<ComposantÉvaluation>
 }
 
 #[derive(Clone)]
 pub struct Évaluation {
-    idx: u32,
     pub nom: String,
     pub sections: Vec<SectionÉvaluation>
 }
 
 impl ClientClasseur {
     pub fn new(fichier: &str) -> Result<ClientClasseur> {
-        let ods = read_ods(fichier)?;
+        let ods = read_ods(fichier).contexte(|| format!("Fichier {}", fichier))?;
 
-        Ok(Self { ods: ods })
+        Ok(Self { ods })
     }
 
     fn obtenir_cours(&self) -> Result<Vec<Cours>> {
@@ -66,7 +90,9 @@ impl ClientClasseur {
         Ok((0..self.ods.num_sheets()).filter_map(|idx| {
             let nom = self.ods.sheet(idx).name();
             re_code_cours.is_match(nom).then(|| Cours {
-                idx: idx,
+                idx,
+                colonne_encompass: self.colonne_encompass(idx),
+                feuille: nom.into(),
                 code: nom.into(),
                 évaluations: Vec::new(),
                 élèves: Vec::new()
@@ -74,6 +100,13 @@ impl ClientClasseur {
         }).collect())
     }
 
+    // Une colonne facultative, après les colonnes de l'élève, dont le premier en-tête est « EnCompass », donne l'identifiant
+    // EnCompass de l'élève. Elle n'est pas une évaluation.
+    fn colonne_encompass(&self, idx: usize) -> Option<u32> {
+        let feuille = self.ods.sheet(idx);
+        (DÉCALAGE_NOTES..feuille.used_cols()).find(|&colonne| cellule_str(feuille.value(0, colonne)).eq_ignore_ascii_case("EnCompass"))
+    }
+
     fn obtenir_élèves(&self, cours: &Cours) -> Result<Vec<Cours>> {
         let feuille = self.ods.sheet(cours.idx);
         let n_lignes = feuille.used_rows();
@@ -85,10 +118,6 @@ impl ClientClasseur {
                 continue
             }
 
-            let étiquettes = cellule_str(feuille.value(ligne, 1));
-            let est_virtuel = étiquettes.contains("V");
-            let est_ap = étiquettes.contains("AP");
-
             let nom = cellule_str(feuille.value(ligne, 2));
             let prénom = cellule_str(feuille.value(ligne, 3));
             let nom_cours = cellule_str(feuille.value(ligne, 4));
@@ -97,13 +126,14 @@ impl ClientClasseur {
             cours_élève.code = nom_cours;
             cours_élève.élèves.push(Élève {
                 idx: ligne,
-                prénom: prénom,
-                nom: nom,
-                prénom_préféré: prénom_préféré,
-                étiquettes: vec![est_virtuel.then(|| "Virtuel"), est_ap.then(|| "AP")]
-                    .iter()
-                    .filter_map(|x| x.map(|x| x.to_string()))
-                    .collect(),
+                id_encompass: cours.colonne_encompass.and_then(|colonne| match feuille.value(ligne, colonne) {
+                    Value::Number(n) if n.fract() == 0. => Some(*n as i64),
+                    v => cellule_str(v).parse().ok()
+                }),
+                prénom,
+                nom,
+                prénom_préféré,
+                étiquettes: étiquettes_classeur(&cellule_str(feuille.value(ligne, 1))),
                 notes: Vec::new()
             });
         }
@@ -118,13 +148,15 @@ impl ClientClasseur {
         let mut idx_dernier = 0;
         let mut évaluations = Vec::new();
         for colonne in DÉCALAGE_NOTES..n_colonnes {
+            if Some(colonne) == cours.colonne_encompass {
+                continue;
+            }
             let nom_évaluation = cellule_str(feuille.value(0, colonne));
             let nom_section = cellule_str(feuille.value(1, colonne));
             let nom_composant = cellule_str(feuille.value(2, colonne));
 
             if !nom_évaluation.is_empty() {
                 évaluations.push(Évaluation {
-                    idx: idx_dernier,
                     nom: nom_évaluation,
                     sections: Vec::new()
                 });
@@ -133,7 +165,6 @@ impl ClientClasseur {
             if !nom_section.is_empty() {
                 if let Some(évaluation) = évaluations.last_mut() {
                     évaluation.sections.push(SectionÉvaluation {
-                        idx: idx_dernier,
                         nom: nom_section,
                         composants: Vec::new()
                     });
@@ -145,7 +176,7 @@ impl ClientClasseur {
                     if let Some(section) = évaluation.sections.last_mut() {
                         section.composants.push(ComposantÉvaluation {
                             idx: idx_dernier,
-                            colonne: colonne,
+                            colonne,
                             nom: nom_composant
                         });
                         idx_dernier += 1;
@@ -178,15 +209,63 @@ impl ClientClasseur {
         Ok(notes)
     }
 
+    pub fn obtenir_contacts_manuels(&self) -> Result<Vec<ContactManuel>> {
+        let feuille = match self.ods.sheet_idx("Contacts") {
+            Some(idx) => self.ods.sheet(idx),
+            None => return Ok(Vec::new())
+        };
+
+        let optionnel = |ligne, colonne| {
+            let val = cellule_str(feuille.value(ligne, colonne));
+            if val.is_empty() { None } else { Some(val) }
+        };
+        let booléen = |ligne, colonne| {
+            match cellule_str(feuille.value(ligne, colonne)).to_lowercase().as_str() {
+                "o" | "oui" | "v" => Some(true),
+                "n" | "non" | "f" => Some(false),
+                _ => None
+            }
+        };
+
+        let mut contacts = Vec::new();
+        for ligne in 1..feuille.used_rows() {
+            let cours = cellule_str(feuille.value(ligne, 0));
+            let prénom = cellule_str(feuille.value(ligne, 1));
+            let nom = cellule_str(feuille.value(ligne, 2));
+            let nom_complet = cellule_str(feuille.value(ligne, 3));
+            if cours.is_empty() || prénom.is_empty() || nom.is_empty() || nom_complet.is_empty() {
+                continue
+            }
+
+
+            contacts.push(ContactManuel {
+                cours,
+                prénom,
+                nom,
+                nom_complet,
+                relation: optionnel(ligne, 4),
+                correspondance: booléen(ligne, 5),
+                consentement: booléen(ligne, 10),
+                majeur: booléen(ligne, 11),
+                courriel: optionnel(ligne, 6),
+                tel_domicile: optionnel(ligne, 7),
+                tel_travail: optionnel(ligne, 8),
+                tel_cellulaire: optionnel(ligne, 9)
+            });
+        }
+
+        Ok(contacts)
+    }
+
     pub fn obtenir_données(&self) -> Result<Vec<Cours>> {
         let mut cours = Vec::new();
-        for mut c in &mut self.obtenir_cours()? {
-            c.évaluations = self.obtenir_évaluations(&c)?;
+        for c in &mut self.obtenir_cours()? {
+            c.évaluations = self.obtenir_évaluations(c)?;
 
-            let mut sous_cours = self.obtenir_élèves(&c)?;
+            let mut sous_cours = self.obtenir_élèves(c)?;
             for sc in &mut sous_cours {
-                for mut élève in &mut sc.élèves {
-                    élève.notes = self.obtenir_notes(&c, &élève)?;
+                for élève in &mut sc.élèves {
+                    élève.notes = self.obtenir_notes(c, élève)?;
                 }
             }
             cours.extend(sous_cours);
@@ -196,26 +275,102 @@ impl ClientClasseur {
     }
 }
 
-fn xml_str(v: &Vec<XmlContent>) -> String {
+// Retourne un classeur avec ses styles de cellule par défaut et en gras.
+pub fn nouveau_classeur() -> (WorkBook, CellStyleRef, CellStyleRef) {
+    let mut wb = WorkBook::new();
+
+    let mut fonte = FontFaceDecl::new_with_name("Palatino Linotype");
+    fonte.set_font_family("Palatino Linotype");
+    fonte.set_font_family_generic("roman");
+    wb.add_font(fonte);
+
+    let mut défaut = CellStyle::empty();
+    défaut.set_name("Défaut");
+    défaut.set_font_name("Palatino Linotype");
+    défaut.set_font_size(Length::Pt(12.));
+    let défaut_ref = wb.add_cellstyle(défaut);
+
+    let mut gras = CellStyle::empty();
+    gras.set_name("Gras");
+    gras.set_font_name("Palatino Linotype");
+    gras.set_font_bold();
+    gras.set_font_size(Length::Pt(12.));
+    let gras_ref = wb.add_cellstyle(gras);
+
+    (wb, défaut_ref, gras_ref)
+}
+
+// La colonne contenait auparavant « V » pour les élèves virtuels et « AP », p. ex. « V AP » ou « VAP »; ces valeurs
+// donnent les étiquettes Virtuel et AP, créées à l'époque. Sinon, la colonne contient des étiquettes séparées par des
+// virgules, où « V » désigne aussi l'étiquette Virtuel.
+fn étiquettes_classeur(texte: &str) -> Vec<String> {
+    static R_ANCIENNE: OnceLock<Regex> = OnceLock::new();
+    let r_ancienne = R_ANCIENNE.get_or_init(|| Regex::new(r"^\s*(?:V|AP)(?:[\s,/]*(?:V|AP))*\s*$").unwrap());
+    if r_ancienne.is_match(texte) {
+        let mut étiquettes = Vec::new();
+        if texte.replace("AP", "").contains('V') {
+            étiquettes.push("Virtuel".to_string());
+        }
+        if texte.contains("AP") {
+            étiquettes.push("AP".to_string());
+        }
+        return étiquettes;
+    }
+
+    let mut étiquettes: Vec<String> = Vec::new();
+    for étiquette in lire_étiquettes(texte) {
+        let étiquette = if étiquette == "V" { "Virtuel".to_string() } else { étiquette };
+        if !étiquettes.contains(&étiquette) {
+            étiquettes.push(étiquette);
+        }
+    }
+
+    étiquettes
+}
+
+fn xml_str(v: &[XmlContent]) -> String {
     v.iter()
         .map(|x| match x {
             XmlContent::Text(t) => t.clone(),
             XmlContent::Tag(t) => xml_str(t.content())
         })
         .reduce(|a, b| a + " " + &b)
-        .unwrap()
+        .unwrap_or_default()
 }
 
 fn cellule_str(v: &Value) -> String {
     match v {
         Value::Text(t) => t.trim().to_string(),
-        Value::TextXml(t) => xml_str(&t.iter().map(|x| XmlContent::Tag(x.clone())).collect()).trim().to_string(),
+        Value::TextXml(t) => xml_str(&t.iter().map(|x| XmlContent::Tag(x.clone())).collect::<Vec<_>>()).trim().to_string(),
         Value::Boolean(b) => if *b { "v" } else { "f" }.to_string(),
         Value::Number(n) => n.to_string(),
         Value::Percentage(n) => (n * 100.).to_string() + "%",
-        Value::Currency(n, devise) => n.to_string() + &std::str::from_utf8(devise).unwrap(),
+        Value::Currency(n, devise) => n.to_string() + &String::from_utf8_lossy(devise),
         Value::DateTime(t) => t.format("%Y-%m-%d %H:%M:%S").to_string(),
-        Value::TimeDuration(d) => d.to_std().unwrap().as_secs_f32().to_string(),
+        Value::TimeDuration(d) => (d.num_milliseconds() as f32 / 1000.).to_string(),
         _ => "".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn étiquettes() {
+        let cas: &[(&str, &[&str])] = &[
+            ("", &[]),
+            ("V", &["Virtuel"]),
+            ("AP", &["AP"]),
+            ("V AP", &["Virtuel", "AP"]),
+            ("VAP", &["Virtuel", "AP"]),
+            ("AP, V", &["Virtuel", "AP"]),
+            ("PEI, V", &["PEI", "Virtuel"]),
+            ("Virtuel, Virtuel, Sport", &["Virtuel", "Sport"]),
+            ("Avancé", &["Avancé"])
+        ];
+        for (texte, attendues) in cas {
+            assert_eq!(étiquettes_classeur(texte), attendues.to_vec(), "{:?}", texte);
+        }
+    }
+}
@@ -1,267 +1,1332 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+use std::path::Path;
 use chrono::Local;
-use regex::Regex;
-use rusqlite::{Connection, params};
-use spreadsheet_ods::{CellStyle, read_ods, Sheet, style::{FontFaceDecl, units::Length}, WorkBook, write_ods};
-use crate::classeur::ClientClasseur;
-use crate::erreur::Result;
+use log::{debug, info, warn};
+use rusqlite::{Connection, OptionalExtension, params};
+use spreadsheet_ods::{Sheet, write_ods};
+use crate::bd;
+use crate::classeur::{ClientClasseur, nouveau_classeur};
+use crate::coordonnees;
+use crate::encompass;
+use crate::erreur::{Erreur, Result};
+use crate::etiquettes::{filtrer_élèves, étiqueter};
+use crate::evaluations::{charger_évaluations, chemin};
 use crate::ilc_encompass;
+use crate::sessions::trouver_cours;
 
-pub fn importer_encompass(conn: &mut Connection) -> Result<()> {
-    let insérer_contact = |id_contact, item, type_item| -> Result<()> {
-        if let Some(val) = item {
+// Les élèves sont associés selon leur identifiant EnCompass, sinon selon leur nom s'ils ont été importés avant qu'il soit
+// conservé.
+fn obtenir_élève(conn: &Connection, élève: &encompass::Élève) -> Result<i64> {
+    let id_élève = conn.query_row("
+        SELECT id FROM élève WHERE id_encompass = ?1;
+    ", [élève.id], |r| r.get(0)).optional()?;
+    let id_élève = match id_élève {
+        Some(id_élève) => Some(id_élève),
+        None => conn.query_row("
+            SELECT id FROM élève WHERE prénom = ?1 AND nom = ?2 AND id_encompass IS NULL ORDER BY id LIMIT 1;
+        ", params![élève.prénom, élève.nom], |r| r.get(0)).optional()?
+    };
+
+    let naissance = élève.naissance.map(|n| n.to_string());
+    let id_élève = match id_élève {
+        Some(id_élève) => {
+            conn.execute("
+                UPDATE élève SET id_encompass = ?1, prénom = ?2, nom = ?3, naissance = COALESCE(?4, naissance), majeur_encompass = ?5
+                    WHERE id = ?6;
+            ", params![élève.id, élève.prénom, élève.nom, naissance, élève.majeur, id_élève])?;
+            id_élève
+        },
+        None => {
             conn.execute("
+                INSERT INTO élève(id_encompass, prénom, nom, naissance, majeur_encompass) VALUES (?1, ?2, ?3, ?4, ?5);
+            ", params![élève.id, élève.prénom, élève.nom, naissance, élève.majeur])?;
+            conn.last_insert_rowid()
+        }
+    };
+
+    // Les indicateurs absents du tableau de suivi et les champs absents de la fiche sont conservés. Le niveau du tableau
+    // de suivi a préséance sur celui de la fiche.
+    let suivi = &élève.suivi;
+    let fiche = &élève.fiche;
+    let courriel = fiche.courriel.as_deref().map(coordonnees::normaliser_courriel).filter(|courriel| {
+        let valide = coordonnees::courriel_valide(courriel);
+        if !valide {
+            warn!("Le courriel {} de {} {} est invalide.", courriel, élève.prénom, élève.nom);
+        }
+        valide
+    });
+    conn.execute("
+        UPDATE élève SET niveau = COALESCE(?1, ?2, niveau), crédits = COALESCE(?3, crédits), pei = COALESCE(?4, pei), alf = COALESCE(?5, alf),
+                courriel = COALESCE(?6, courriel), niso = COALESCE(?7, niso), foyer = COALESCE(?8, foyer), conseiller = COALESCE(?9, conseiller)
+            WHERE id = ?10;
+    ", params![suivi.niveau, fiche.niveau, suivi.crédits, suivi.pei, suivi.alf, courriel, fiche.niso, fiche.foyer, fiche.conseiller, id_élève])?;
+    for (nom, valeur) in &suivi.indicateurs {
+        conn.execute("
+            INSERT INTO élève_indicateur(id_élève, nom, valeur) VALUES (?1, ?2, ?3)
+                ON CONFLICT(id_élève, nom) DO UPDATE SET valeur = excluded.valeur;
+        ", params![id_élève, nom, valeur])?;
+    }
+
+    // Un élève majeur selon sa date de naissance, sans le bloc de l'élève majeur d'EnCompass, n'a consenti à aucun contact.
+    let majeur: bool = conn.query_row("SELECT majeur FROM élève_statut WHERE id_élève = ?1", [id_élève], |r| r.get(0))?;
+    if majeur && !élève.majeur {
+        warn!(
+            "{} {} est majeur selon sa date de naissance, mais EnCompass n'indique pas ses consentements; ses contacts ne sont pas autorisés sans consentement manuel.",
+            élève.prénom, élève.nom
+        );
+    }
+
+    Ok(id_élève)
+}
+
+pub fn importer_encompass(conn: &mut Connection, id_session: i64, portail: &encompass::Portail) -> Result<()> {
+    let insérer_contact = |id_contact, item, type_item| -> Result<usize> {
+        match item {
+            Some(val) => Ok(conn.execute("
                 INSERT OR IGNORE INTO élève_contact_item(id_contact, id_type, coordonnée, automatique)
                     SELECT ?1, id, ?2, 1
                         FROM élève_contact_type WHERE type = ?3;
-            ", params![id_contact, val, type_item])?;
+            ", params![id_contact, val, type_item])?),
+            None => Ok(0)
         }
-
-        Ok(())
     };
 
-    if conn.query_row("SELECT COUNT(*) FROM élève;", [], |r| r.get(0)).unwrap_or(0) == 0 {
+    let n_élèves: i64 = conn.query_row("
+        SELECT COUNT(*)
+            FROM inscription
+            JOIN cours ON cours.id = inscription.id_cours
+            WHERE cours.id_session = ?1;
+    ", [id_session], |r| r.get(0))?;
+    if n_élèves > 0 {
+        debug!("La session compte déjà {} inscriptions; EnCompass n'est pas consulté.", n_élèves);
+    } else {
         // TODO: 1. delete contacts_item automatiques
         //     2. delete contacts automatiques qui n'ont aucun item
-        for cours in ilc_encompass::obtenir_contacts()? {
-            conn.execute("INSERT OR IGNORE INTO cours(code) VALUES (?1)", [&cours.code])?;
-            let id_cours: i64 = conn.query_row("SELECT id FROM cours WHERE code = ?1", [&cours.code], |r| r.get(0))?;
+        for cours in ilc_encompass::obtenir_contacts(portail)? {
+            let id_cours = match trouver_cours(conn, &cours.code, id_session)? {
+                Some(id_cours) => {
+                    conn.execute("UPDATE cours SET id_groupe = ?1 WHERE id = ?2", params![cours.id_groupe, id_cours])?;
+                    id_cours
+                },
+                None => {
+                    conn.execute("
+                        INSERT INTO cours(code, id_session, id_groupe) VALUES (?1, ?2, ?3)
+                    ", params![cours.code, id_session, cours.id_groupe])?;
+                    conn.last_insert_rowid()
+                }
+            };
 
+            let (mut n_inscriptions, mut n_contacts, mut n_coordonnées) = (0, 0, 0);
             for élève in cours.élèves {
-                conn.execute("INSERT OR IGNORE INTO élève(prénom, nom, id_cours) VALUES (?1, ?2, ?3)", params![élève.prénom, élève.nom, id_cours])?;
-                let id_élève: i64 = conn.query_row("SELECT id FROM élève WHERE prénom = ?1 AND nom = ?2 AND id_cours = ?3", params![élève.prénom, élève.nom, id_cours], |r| r.get(0))?;
+                let id_élève = obtenir_élève(conn, &élève)?;
+                n_inscriptions += conn.execute("INSERT OR IGNORE INTO inscription(id_élève, id_cours) VALUES (?1, ?2)", params![id_élève, id_cours])?;
 
                 for contact in élève.contacts {
-                    if contact.tel_domicile.is_none() && contact.tel_travail.is_none() && contact.tel_cellulaire.is_none() && contact.courriel.is_none() {
-                        continue
-                    }
-
-                    conn.execute(
-                        "INSERT OR IGNORE INTO élève_contact(id_élève, nom_complet, relation, correspondance, automatique, ordre) VALUES (?1, ?2, ?3, ?4, 1, ?5)",
-                        params![id_élève, contact.nom_complet, contact.relation, contact.correspondance, contact.ordre]
-                    )?;
+                    // Les contacts sans coordonnée sont conservés pour le rapport de diagnostic.
+                    // Les contacts modifiés manuellement ne sont jamais écrasés.
+                    n_contacts += conn.execute("
+                        INSERT INTO élève_contact(id_élève, nom_complet, relation, correspondance, automatique, ordre) VALUES (?1, ?2, ?3, ?4, 1, ?5)
+                            ON CONFLICT(id_élève, nom_complet) DO UPDATE SET
+                                relation = excluded.relation,
+                                correspondance = excluded.correspondance,
+                                ordre = excluded.ordre
+                            WHERE automatique = 1;
+                    ", params![id_élève, contact.nom_complet, contact.relation, contact.correspondance, contact.ordre])?;
                     let id_contact: i64 = conn.query_row("SELECT id FROM élève_contact WHERE id_élève = ?1 AND nom_complet = ?2", params![id_élève, contact.nom_complet], |r| r.get(0))?;
+                    // Le consentement d'EnCompass est conservé même pour un contact modifié manuellement.
+                    conn.execute("UPDATE élève_contact SET consentement = ?1 WHERE id = ?2", params![contact.consentement, id_contact])?;
 
-                    insérer_contact(id_contact, contact.tel_domicile, &"Téléphone au domicile")?;
-                    insérer_contact(id_contact, contact.tel_travail, &"Téléphone au travail")?;
-                    insérer_contact(id_contact, contact.tel_cellulaire, &"Téléphone cellulaire")?;
-                    insérer_contact(id_contact, contact.courriel, &"Courriel")?;
+                    n_coordonnées += insérer_contact(id_contact, contact.tel_domicile, &"Téléphone au domicile")?;
+                    n_coordonnées += insérer_contact(id_contact, contact.tel_travail, &"Téléphone au travail")?;
+                    n_coordonnées += insérer_contact(id_contact, contact.tel_cellulaire, &"Téléphone cellulaire")?;
+                    n_coordonnées += insérer_contact(id_contact, contact.courriel, &"Courriel")?;
                 }
             }
+            debug!("cours={} inscriptions={} contacts={} coordonnées={}", cours.code, n_inscriptions, n_contacts, n_coordonnées);
         }
     }
 
+    normaliser_coordonnées(conn)?;
+    regrouper_tuteurs(conn)?;
+
     Ok(())
 }
 
-pub fn importer_notes_classeur(conn: &mut Connection) -> Result<()> {
-    let classeur = ClientClasseur::new(r"évaluations.ods")?;
+pub fn normaliser_coordonnées(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction()?;
 
-    conn.execute_batch("
-        BEGIN;
+    let items: Vec<(i64, String, String)> = tx
+        .prepare("
+            SELECT i.rowid, t.type, i.coordonnée
+                FROM élève_contact_item AS i
+                JOIN élève_contact_type AS t ON t.id = i.id_type
+                WHERE i.valide IS NULL;")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut n_normalisées = 0;
+    for (rowid, type_item, coordonnée) in items {
+        let (normalisée, valide) = coordonnees::normaliser(&type_item, &coordonnée);
+        n_normalisées += tx.execute("
+            UPDATE élève_contact_item SET normalisée = ?1, valide = ?2 WHERE rowid = ?3;
+        ", params![normalisée, valide, rowid])?;
+    }
+    debug!("coordonnées normalisées={}", n_normalisées);
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+pub fn regrouper_tuteurs(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction()?;
+
+    // (id du contact, nom complet, tuteur actuel, coordonnées)
+    let mut contacts: Vec<(i64, String, Option<i64>, Vec<String>)> = Vec::new();
+    {
+        let mut stmt = tx.prepare("
+            SELECT c.id, c.nom_complet, tc.id_tuteur, COALESCE(i.normalisée, LOWER(TRIM(i.coordonnée)))
+                FROM élève_contact AS c
+                LEFT JOIN tuteur_contact AS tc ON tc.id_contact = c.id
+                LEFT JOIN élève_contact_item AS i ON i.id_contact = c.id
+                ORDER BY c.id;")?;
+        let mut req = stmt.query([])?;
+        while let Some(r) = req.next()? {
+            let id: i64 = r.get(0)?;
+            if contacts.last().map(|c| c.0) != Some(id) {
+                contacts.push((id, r.get(1)?, r.get(2)?, Vec::new()));
+            }
+            if let Some(coordonnée) = r.get::<_, Option<String>>(3)? {
+                contacts.last_mut().unwrap().3.push(coordonnée);
+            }
+        }
+    }
+
+    // Deux contacts sont le même tuteur s'ils partagent le nom et au moins une coordonnée.
+    fn racine(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    let mut parents: Vec<usize> = (0..contacts.len()).collect();
+    let mut vus: HashMap<(String, String), usize> = HashMap::new();
+    for (i, (_, nom, _, coordonnées)) in contacts.iter().enumerate() {
+        let nom = nom.trim().to_lowercase();
+        for coordonnée in coordonnées {
+            match vus.entry((nom.clone(), coordonnée.clone())) {
+                Entry::Occupied(e) => {
+                    let a = racine(&mut parents, *e.get());
+                    let b = racine(&mut parents, i);
+                    parents[b] = a;
+                },
+                Entry::Vacant(e) => {
+                    e.insert(i);
+                }
+            }
+        }
+    }
+
+    let mut groupes: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..contacts.len() {
+        let r = racine(&mut parents, i);
+        groupes.entry(r).or_default().push(i);
+    }
+
+    let mut groupes: Vec<_> = groupes.into_values().collect();
+    groupes.sort();
+
+    debug!("contacts={} tuteurs={}", contacts.len(), groupes.len());
+    let mut tuteurs_utilisés = Vec::new();
+    for membres in groupes {
+        let id_tuteur = match membres
+            .iter()
+            .filter_map(|&i| contacts[i].2)
+            .filter(|id| !tuteurs_utilisés.contains(id))
+            .min() {
+            Some(id) => id,
+            None => {
+                tx.execute("INSERT INTO tuteur(nom_complet) VALUES (?1)", [&contacts[membres[0]].1])?;
+                tx.last_insert_rowid()
+            }
+        };
+        tuteurs_utilisés.push(id_tuteur);
+
+        for i in membres {
+            tx.execute("
+                INSERT INTO tuteur_contact(id_tuteur, id_contact) VALUES (?1, ?2)
+                    ON CONFLICT(id_contact) DO UPDATE SET id_tuteur = excluded.id_tuteur;
+            ", params![id_tuteur, contacts[i].0])?;
+        }
+    }
+
+    tx.execute_batch("
+        DELETE FROM tuteur_contact WHERE id_contact NOT IN (SELECT id FROM élève_contact);
+        DELETE FROM tuteur WHERE id NOT IN (SELECT id_tuteur FROM tuteur_contact);
+    ")?;
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+// La coordonnée manuelle remplace celles du même type pour tous les contacts du même tuteur.
+pub fn remplacer_coordonnée(conn: &Connection, id_contact: i64, type_item: &str, coordonnée: &str) -> Result<()> {
+    conn.execute("
+        DELETE FROM élève_contact_item
+            WHERE automatique = 0
+                AND id_type = (SELECT id FROM élève_contact_type WHERE type = ?2)
+                AND id_contact IN (
+                    SELECT ?1
+                    UNION
+                    SELECT tc2.id_contact
+                        FROM tuteur_contact AS tc1
+                        JOIN tuteur_contact AS tc2 ON tc2.id_tuteur = tc1.id_tuteur
+                        WHERE tc1.id_contact = ?1
+                );
+    ", params![id_contact, type_item])?;
+
+    conn.execute("
+        INSERT OR IGNORE INTO élève_contact_item(id_contact, id_type, coordonnée, automatique)
+            SELECT c.id, t.id, ?3, 0
+                FROM élève_contact AS c, élève_contact_type AS t
+                WHERE t.type = ?2 AND c.id IN (
+                    SELECT ?1
+                    UNION
+                    SELECT tc2.id_contact
+                        FROM tuteur_contact AS tc1
+                        JOIN tuteur_contact AS tc2 ON tc2.id_tuteur = tc1.id_tuteur
+                        WHERE tc1.id_contact = ?1
+                );
+    ", params![id_contact, type_item, coordonnée])?;
+
+    Ok(())
+}
+
+pub fn importer_contacts_manuels(conn: &mut Connection, id_session: i64) -> Result<()> {
+    if !Path::new("contacts_manuels.ods").exists() {
+        return Ok(());
+    }
+
+    let classeur = ClientClasseur::new(r"contacts_manuels.ods")?;
+
+    let tx = conn.transaction()?;
+    let mut n_contacts = 0;
+    for contact in classeur.obtenir_contacts_manuels()? {
+        let id_élève: Option<i64> = tx.query_row("
+            SELECT élève.id
+                FROM élève
+                JOIN inscription ON inscription.id_élève = élève.id
+                JOIN cours ON cours.id = inscription.id_cours
+                WHERE cours.code = ?1 AND élève.prénom = ?2 AND élève.nom = ?3 AND cours.id_session = ?4;
+        ", params![contact.cours, contact.prénom, contact.nom, id_session], |r| r.get(0)).optional()?;
+        let id_élève = match id_élève {
+            Some(id) => id,
+            None => {
+                warn!("Élève introuvable: {} {} {}", contact.cours, contact.prénom, contact.nom);
+                continue
+            }
+        };
+
+        n_contacts += tx.execute("
+            INSERT INTO élève_contact(id_élève, nom_complet, relation, correspondance, automatique, consentement_manuel)
+                VALUES (?1, ?2, ?3, COALESCE(?4, 1), 0, ?5)
+                ON CONFLICT(id_élève, nom_complet) DO UPDATE SET
+                    relation = COALESCE(excluded.relation, relation),
+                    correspondance = COALESCE(?4, correspondance),
+                    automatique = 0,
+                    consentement_manuel = COALESCE(?5, consentement_manuel);
+        ", params![id_élève, contact.nom_complet, contact.relation, contact.correspondance, contact.consentement])?;
+        if let Some(majeur) = contact.majeur {
+            tx.execute("UPDATE élève SET majeur_manuel = ?1 WHERE id = ?2", params![majeur, id_élève])?;
+        }
+        let id_contact: i64 = tx.query_row("SELECT id FROM élève_contact WHERE id_élève = ?1 AND nom_complet = ?2", params![id_élève, contact.nom_complet], |r| r.get(0))?;
+
+        for (coordonnée, type_item) in [
+            (&contact.tel_domicile, "Téléphone au domicile"),
+            (&contact.tel_travail, "Téléphone au travail"),
+            (&contact.tel_cellulaire, "Téléphone cellulaire"),
+            (&contact.courriel, "Courriel")
+        ] {
+            if let Some(coordonnée) = coordonnée {
+                remplacer_coordonnée(&tx, id_contact, type_item, coordonnée)?;
+            }
+        }
+    }
+    tx.commit()?;
+    debug!("contacts manuels={}", n_contacts);
 
-        DELETE FROM échelle_niveau;
-        DELETE FROM échelle;
-        DELETE FROM évaluation_résultat;
-        DELETE FROM évaluation_reprise;
-        DELETE FROM évaluation_item;
+    normaliser_coordonnées(conn)?;
+    regrouper_tuteurs(conn)?;
 
-        INSERT INTO échelle(id, nom, précision, min, max) VALUES
-            (1, 'Niveau', 4, 0, 4),
-            (2, 'Pourcentage', 0, 0, 100);
+    Ok(())
+}
+
+// Retourne l'item du cours qui porte ce nom sous l'ascendant donné, en l'ajoutant à la fin de ses voisins au besoin.
+fn obtenir_item(conn: &Connection, id_cours: i64, id_parent: Option<i64>, nom: &str) -> Result<(i64, bool)> {
+    let id_item = conn.query_row("
+        SELECT id FROM évaluation_item WHERE id_cours = ?1 AND id_parent IS ?2 AND nom = ?3;
+    ", params![id_cours, id_parent, nom], |r| r.get(0)).optional()?;
+    if let Some(id_item) = id_item {
+        return Ok((id_item, false));
+    }
 
-        COMMIT;
+    conn.execute("
+        INSERT INTO évaluation_item(nom, id_cours, id_parent, indice)
+            SELECT ?1, ?2, ?3, COALESCE(MAX(indice) + 1, 0)
+            FROM évaluation_item
+            WHERE id_cours = ?2 AND id_parent IS ?3;
+    ", params![nom, id_cours, id_parent])?;
+
+    Ok((conn.last_insert_rowid(), true))
+}
+
+fn créer_échelles(conn: &Connection) -> Result<()> {
+    conn.execute_batch("
+        INSERT INTO échelle(nom, précision, min, max)
+            SELECT 'Niveau', 4, 0, 4
+            WHERE NOT EXISTS (SELECT 1 FROM échelle WHERE nom = 'Niveau');
+        INSERT INTO échelle(nom, précision, min, max)
+            SELECT 'Pourcentage', 0, 0, 100
+            WHERE NOT EXISTS (SELECT 1 FROM échelle WHERE nom = 'Pourcentage');
     ")?;
 
+    bd::créer_niveaux(conn)
+}
+
+enum Modification {
+    Ajout(f64),
+    Changement(f64, f64),
+    Suppression(f64)
+}
+
+// Seuls les résultats de la première tentative saisis manuellement sont modifiés; les reprises et les résultats calculés
+// sont conservés.
+fn modifier_résultat(conn: &Connection, id_item: i64, id_élève: i64, note: Option<f64>) -> Result<Option<Modification>> {
+    let existant: Option<(Option<f64>, Option<f64>)> = conn.query_row("
+        SELECT résultat, résultat_auto
+            FROM évaluation_résultat
+            WHERE id_item = ?1 AND id_élève = ?2 AND id_reprise IS NULL;
+    ", params![id_item, id_élève], |r| Ok((r.get(0)?, r.get(1)?))).optional()?;
+
+    match (existant, note) {
+        (None, Some(n)) => {
+            conn.execute("
+                INSERT INTO évaluation_résultat(id_item, id_élève, résultat)
+                    VALUES (?1, ?2, ?3);
+            ", params![id_item, id_élève, n])?;
+            Ok(Some(Modification::Ajout(n)))
+        },
+        (Some((résultat, _)), Some(n)) if résultat != Some(n) => {
+            conn.execute("
+                UPDATE évaluation_résultat SET résultat = ?3
+                    WHERE id_item = ?1 AND id_élève = ?2 AND id_reprise IS NULL;
+            ", params![id_item, id_élève, n])?;
+            match résultat {
+                Some(r) => Ok(Some(Modification::Changement(r, n))),
+                None => Ok(Some(Modification::Ajout(n)))
+            }
+        },
+        (Some((Some(r), résultat_auto)), None) => {
+            if résultat_auto.is_some() {
+                conn.execute("
+                    UPDATE évaluation_résultat SET résultat = NULL
+                        WHERE id_item = ?1 AND id_élève = ?2 AND id_reprise IS NULL;
+                ", params![id_item, id_élève])?;
+            } else {
+                conn.execute("
+                    DELETE FROM évaluation_résultat
+                        WHERE id_item = ?1 AND id_élève = ?2 AND id_reprise IS NULL;
+                ", params![id_item, id_élève])?;
+            }
+            Ok(Some(Modification::Suppression(r)))
+        },
+        _ => Ok(None)
+    }
+}
+
+#[derive(Default)]
+struct Bilan {
+    ajouts: usize,
+    changements: usize,
+    suppressions: usize,
+    orphelins: Vec<String>
+}
+
+impl Bilan {
+    fn noter(&mut self, item: &str, élève: &str, modification: Option<Modification>) {
+        match modification {
+            Some(Modification::Ajout(n)) => {
+                info!("+ {}, {}: {}", item, élève, n);
+                self.ajouts += 1;
+            },
+            Some(Modification::Changement(r, n)) => {
+                info!("~ {}, {}: {} → {}", item, élève, r, n);
+                self.changements += 1;
+            },
+            Some(Modification::Suppression(r)) => {
+                info!("- {}, {}: {}", item, élève, r);
+                self.suppressions += 1;
+            },
+            None => ()
+        }
+    }
+
+    fn résumer(&self) {
+        info!("Résultats ajoutés: {}, modifiés: {}, retirés: {}", self.ajouts, self.changements, self.suppressions);
+        for orphelin in &self.orphelins {
+            warn!("? {}: absent du classeur; l'item et ses résultats sont conservés.", orphelin);
+        }
+    }
+}
+
+// Retourne le chemin des items du cours qui n'ont pas été vus lors de l'importation, sans leurs descendants.
+fn items_orphelins(conn: &Connection, id_cours: i64, vus: &HashSet<i64>) -> Result<Vec<String>> {
+    let évaluations = charger_évaluations(conn, id_cours)?;
+
+    let mut orphelins = Vec::new();
+    for racine in évaluations.racines() {
+        for id in évaluations.parcours_préfixe(racine)? {
+            let ascendant_vu = match évaluations.ascendants(id)?.next() {
+                Some(a) => vus.contains(&évaluations.val(a)?.id),
+                None => true
+            };
+            if ascendant_vu && !vus.contains(&évaluations.val(id)?.id) {
+                orphelins.push(chemin(&évaluations, id)?);
+            }
+        }
+    }
+    orphelins.sort();
+
+    Ok(orphelins)
+}
+
+// Les items sont associés selon le chemin de leurs noms et les élèves selon leur identifiant EnCompass (colonne facultative
+// « EnCompass » du classeur), sinon selon leur nom parmi les élèves du cours. Les items absents du classeur sont signalés.
+pub fn importer_notes_classeur(conn: &mut Connection, id_session: i64, fichier: &str) -> Result<()> {
+    let classeur = ClientClasseur::new(fichier)?;
+
+    let tx = conn.transaction()?;
+    créer_échelles(&tx)?;
+
+    let mut bilan = Bilan::default();
     for cours in classeur.obtenir_données()? {
-        let id_cours: i64 = conn.query_row("
-            SELECT id FROM cours WHERE code = ?1
-        ", [cours.code], |r| r.get(0))?;
+        let id_cours = trouver_cours(&tx, &cours.code, id_session)?.ok_or_else(|| Erreur::Classeur {
+            fichier: fichier.into(),
+            feuille: cours.feuille.clone(),
+            ligne: None,
+            message: format!("Le cours {} est introuvable dans la session.", cours.code)
+        })?;
 
         let mut ids_élèves = Vec::new();
         for élève in &cours.élèves {
-            let id_élève: i64 = conn.query_row("
-                SELECT id FROM élève WHERE prénom = ?1 AND nom = ?2 AND id_cours = ?3;
-            ", params![élève.prénom, élève.nom, id_cours], |r| r.get(0))?;
+            let mut stmt = tx.prepare_cached("
+                SELECT élève.id
+                    FROM élève
+                    JOIN inscription ON inscription.id_élève = élève.id
+                    WHERE inscription.id_cours = ?4
+                        AND CASE WHEN ?1 IS NOT NULL THEN élève.id_encompass = ?1 ELSE élève.prénom = ?2 AND élève.nom = ?3 END;
+            ")?;
+            let ids: Vec<i64> = stmt
+                .query_map(params![élève.id_encompass, élève.prénom, élève.nom, id_cours], |r| r.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            let erreur = |message: String| Erreur::Classeur {
+                fichier: fichier.into(),
+                feuille: cours.feuille.clone(),
+                ligne: Some(élève.ligne()),
+                message
+            };
+            let id_élève = match ids[..] {
+                [id_élève] => id_élève,
+                [] => return Err(erreur(format!("L'élève {} {} n'est pas inscrit au cours {}.", élève.prénom, élève.nom, cours.code))),
+                _ => return Err(erreur(format!(
+                    "Plusieurs élèves du cours {} se nomment {} {}; leur identifiant doit être indiqué dans la colonne EnCompass.",
+                    cours.code, élève.prénom, élève.nom
+                )))
+            };
             ids_élèves.push(id_élève);
 
-            conn.execute("
+            tx.execute("
                 UPDATE élève SET prénom_préféré = ?1
                     WHERE id = ?2;
-            ", params![élève.prénom_préféré, id_élève]).unwrap();
+            ", params![élève.prénom_préféré, id_élève])?;
 
             for étiquette in &élève.étiquettes {
-                conn.execute("
-                    INSERT INTO élève_étiquette(id_élève, id_étiquette)
-                        SELECT ?1, étiquette.id
-                        FROM étiquette
-                        WHERE étiquette.nom = ?2;
-                ", params![id_élève, étiquette]).unwrap();
+                étiqueter(&tx, id_élève, étiquette)?;
             }
         }
 
-        for (i, évaluation) in cours.évaluations.iter().enumerate() {
-            conn.execute("
-                INSERT INTO évaluation_item(nom, id_cours, indice) VALUES (?1, ?2, ?3);
-            ", params![évaluation.nom, id_cours, i]).unwrap();
-            let id_évaluation = conn.last_insert_rowid();
+        let mut vus = HashSet::new();
+        for évaluation in &cours.évaluations {
+            let (id_évaluation, nouveau) = obtenir_item(&tx, id_cours, None, &évaluation.nom)?;
+            if nouveau {
+                info!("+ {} {}", cours.code, évaluation.nom);
+            }
+            vus.insert(id_évaluation);
 
-            for (j, section) in évaluation.sections.iter().enumerate() {
-                conn.execute("
-                    INSERT INTO évaluation_item(nom, id_cours, id_parent, indice)
-                        VALUES (?1, ?2, ?3, ?4);
-                ", params![section.nom, id_cours, Some(id_évaluation), j]).unwrap();
-                let id_section = conn.last_insert_rowid();
+            for section in &évaluation.sections {
+                let (id_section, nouveau) = obtenir_item(&tx, id_cours, Some(id_évaluation), &section.nom)?;
+                if nouveau {
+                    info!("+ {} {} / {}", cours.code, évaluation.nom, section.nom);
+                }
+                vus.insert(id_section);
+
+                for composant in &section.composants {
+                    let chemin = format!("{} / {} / {}", évaluation.nom, section.nom, composant.nom);
+                    let (id_composant, nouveau) = obtenir_item(&tx, id_cours, Some(id_section), &composant.nom)?;
+                    if nouveau {
+                        info!("+ {} {}", cours.code, chemin);
+                    }
+                    vus.insert(id_composant);
 
-                for (k, composant) in section.composants.iter().enumerate() {
-                    //println!("!!! {} {} {}", évaluation.nom, section.nom, composant.nom);
-                    conn.execute("
-                        INSERT INTO évaluation_item(nom, id_cours, id_parent, indice)
-                            VALUES (?1, ?2, ?3, ?4);
-                    ", params![composant.nom, id_cours, Some(id_section), k]).unwrap();
-                    let id_composant = conn.last_insert_rowid();
-                
                     for (id_élève, élève) in ids_élèves.iter().zip(&cours.élèves) {
-                        if let Some(n) = élève.note(&composant) {
-                            conn.execute("
-                                INSERT INTO évaluation_résultat(id_item, id_élève, résultat)
-                                    SELECT ?1, élève.id, ?3
-                                    FROM élève
-                                    WHERE prénom = ?1 AND nom = ?2 AND id_cours = ?3;
-                            ", params![Some(id_composant), id_élève, n]).unwrap();
-                        }
+                        let modification = modifier_résultat(&tx, id_composant, *id_élève, élève.note(composant))?;
+                        bilan.noter(&format!("{} {}", cours.code, chemin), &format!("{} {}", élève.prénom, élève.nom), modification);
+                    }
+                }
+            }
+        }
+
+        for orphelin in items_orphelins(&tx, id_cours, &vus)? {
+            bilan.orphelins.push(format!("{} {}", cours.code, orphelin));
+        }
+    }
+
+    tx.commit()?;
+    bilan.résumer();
+
+    Ok(())
+}
+
+// Retourne l'identifiant de chaque groupe EnCompass de la session et les codes de ses cours.
+fn groupes_encompass(conn: &Connection, id_session: i64) -> Result<Vec<(i32, String)>> {
+    let mut stmt = conn.prepare("
+        SELECT id_groupe, GROUP_CONCAT(code, ', ')
+            FROM cours
+            WHERE id_session = ?1 AND id_groupe IS NOT NULL
+            GROUP BY id_groupe
+            ORDER BY MIN(code);
+    ")?;
+    let groupes: Vec<(i32, String)> = stmt
+        .query_map([id_session], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    if groupes.is_empty() {
+        return Err(Erreur::Arguments("Aucun cours de la session n'est associé à un groupe EnCompass; les contacts doivent d'abord être importés.".into()));
+    }
+
+    Ok(groupes)
+}
+
+fn cours_groupe(conn: &Connection, id_session: i64, id_groupe: i32) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare("SELECT id, code FROM cours WHERE id_session = ?1 AND id_groupe = ?2 ORDER BY code")?;
+    let cours = stmt
+        .query_map(params![id_session, id_groupe], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(cours)
+}
+
+// Chaque catégorie du carnet de notes EnCompass devient une évaluation sur l'échelle des pourcentages et chacun de ses
+// travaux, un item de l'évaluation. Les items sont ajoutés aux cours de la session associés au groupe EnCompass et les
+// élèves sont associés selon leur identifiant EnCompass; les notes sont en pourcentage.
+pub fn importer_carnet_encompass(conn: &mut Connection, id_session: i64, portail: &encompass::Portail) -> Result<()> {
+    let groupes = groupes_encompass(conn, id_session)?;
+    let client = ilc_encompass::connecter(portail)?;
+    let carnets = ilc_encompass::obtenir_carnets(&client, &groupes)?;
+
+    let tx = conn.transaction()?;
+    créer_échelles(&tx)?;
+    let id_pourcentage: i64 = tx.query_row("SELECT id FROM échelle WHERE nom = 'Pourcentage'", [], |r| r.get(0))?;
+
+    let mut bilan = Bilan::default();
+    for (id_groupe, carnet) in &carnets {
+        for (id_cours, code) in &cours_groupe(&tx, id_session, *id_groupe)? {
+            let mut ids_items = HashMap::new();
+            for catégorie in &carnet.catégories {
+                let (id_catégorie, nouveau) = obtenir_item(&tx, *id_cours, None, &catégorie.nom)?;
+                if nouveau {
+                    tx.execute("UPDATE évaluation_item SET id_échelle = ?1 WHERE id = ?2", params![id_pourcentage, id_catégorie])?;
+                    info!("+ {} {}", code, catégorie.nom);
+                }
+                tx.execute("UPDATE évaluation_item SET pondération = ?1 WHERE id = ?2", params![catégorie.pondération, id_catégorie])?;
+
+                for travail in carnet.travaux.iter().filter(|t| t.catégorie == catégorie.nom) {
+                    let (id_travail, nouveau) = obtenir_item(&tx, *id_cours, Some(id_catégorie), &travail.nom)?;
+                    if nouveau {
+                        info!("+ {} {} / {}", code, catégorie.nom, travail.nom);
                     }
+                    tx.execute("UPDATE évaluation_item SET pondération = ?1 WHERE id = ?2", params![travail.pondération, id_travail])?;
+                    ids_items.insert(travail.id, (id_travail, format!("{} {} / {}", code, catégorie.nom, travail.nom)));
+                }
+            }
+
+            for note in &carnet.notes {
+                let élève: Option<(i64, String)> = tx.query_row("
+                    SELECT élève.id, élève.prénom || ' ' || élève.nom
+                        FROM élève
+                        JOIN inscription ON inscription.id_élève = élève.id
+                        WHERE élève.id_encompass = ?1 AND inscription.id_cours = ?2;
+                ", params![note.id_élève, id_cours], |r| Ok((r.get(0)?, r.get(1)?))).optional()?;
+                if let (Some((id_élève, nom_élève)), Some((id_item, chemin))) = (élève, ids_items.get(&note.id_travail)) {
+                    let modification = modifier_résultat(&tx, *id_item, id_élève, note.note)?;
+                    bilan.noter(chemin, &nom_élève, modification);
                 }
             }
         }
     }
 
+    tx.commit()?;
+    bilan.résumer();
+
     Ok(())
 }
 
-pub fn exporter_contacts_classeur(conn: &Connection) -> Result<()> {
-    let mut wb = WorkBook::new();
+// Un composant d'évaluation et le travail EnCompass qui lui correspond, avec l'étendue de l'échelle de son évaluation.
+struct Association<'a> {
+    id_item: i64,
+    chemin: String,
+    min: f64,
+    max: f64,
+    travail: &'a encompass::Travail
+}
+
+// Associe chaque composant d'évaluation d'un cours au travail EnCompass du même nom. Si plusieurs travaux portent ce nom,
+// celui dont la catégorie porte le nom de l'évaluation est retenu.
+fn associer_travaux<'a>(conn: &Connection, id_cours: i64, code: &str, carnet: &'a encompass::Carnet) -> Result<Vec<Association<'a>>> {
+    let mut stmt = conn.prepare("
+        WITH RECURSIVE
+            arbre(id, id_racine, chemin) AS (
+                SELECT id, id, nom
+                    FROM évaluation_item
+                    WHERE id_cours = ?1 AND id_parent IS NULL
+                UNION ALL
+                SELECT éi.id, arbre.id_racine, arbre.chemin || ' / ' || éi.nom
+                    FROM évaluation_item AS éi
+                    JOIN arbre ON arbre.id = éi.id_parent
+            )
+        SELECT arbre.id, arbre.chemin, feuille.nom, racine.nom, échelle.min, échelle.max
+            FROM arbre
+            JOIN évaluation_item AS feuille ON feuille.id = arbre.id
+            JOIN évaluation_item AS racine ON racine.id = arbre.id_racine
+            LEFT JOIN échelle ON échelle.id = racine.id_échelle
+            WHERE arbre.id <> arbre.id_racine
+                AND NOT EXISTS (SELECT 1 FROM évaluation_item AS enfant WHERE enfant.id_parent = arbre.id)
+            ORDER BY arbre.chemin;
+    ")?;
+    let mut rangées = stmt.query([id_cours])?;
+
+    let mut associations: Vec<Association> = Vec::new();
+    while let Some(r) = rangées.next()? {
+        let (id_item, chemin, nom, évaluation): (i64, String, String, String) = (r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?);
+        let même_nom = |a: &str, b: &str| a.trim().to_lowercase() == b.trim().to_lowercase();
+
+        let mut travaux: Vec<&encompass::Travail> = carnet.travaux.iter().filter(|t| même_nom(&t.nom, &nom)).collect();
+        if travaux.len() > 1 {
+            travaux.retain(|t| même_nom(&t.catégorie, &évaluation));
+        }
+        let travail = match travaux[..] {
+            [travail] => travail,
+            [] => {
+                debug!("{} {}: aucun travail EnCompass", code, chemin);
+                continue
+            },
+            _ => {
+                warn!("{} {}: plusieurs travaux EnCompass portent ce nom; les notes ne sont pas envoyées.", code, chemin);
+                continue
+            }
+        };
+        if let Some(autre) = associations.iter().find(|a| a.travail.id == travail.id) {
+            warn!("{} {}: le travail EnCompass {} est déjà associé à {}; les notes ne sont pas envoyées.", code, chemin, travail.nom, autre.chemin);
+            continue
+        }
+
+        match (r.get::<_, Option<f64>>(4)?, r.get::<_, Option<f64>>(5)?) {
+            (Some(min), Some(max)) if max > min => associations.push(Association { id_item, chemin, min, max, travail }),
+            _ => warn!("{} {}: l'évaluation n'a pas d'échelle; les notes ne sont pas envoyées.", code, chemin)
+        }
+    }
 
-    let mut fonte = FontFaceDecl::new_with_name("Palatino Linotype");
-    fonte.set_font_family("Palatino Linotype");
-    fonte.set_font_family_generic("roman");
-    wb.add_font(fonte);
+    Ok(associations)
+}
 
-    let mut défaut = CellStyle::empty();
-    défaut.set_name("Défaut");
-    défaut.set_font_name("Palatino Linotype");
-    défaut.set_font_size(Length::Pt(12.));
-    let défaut_ref = wb.add_cellstyle(défaut);
+fn arrondir(note: f64) -> f64 {
+    (note * 100.0).round() / 100.0
+}
 
-    let mut gras = CellStyle::empty();
-    gras.set_name("Gras");
-    gras.set_font_name("Palatino Linotype");
-    gras.set_font_bold();
-    gras.set_font_size(Length::Pt(12.));
-    let gras_ref = wb.add_cellstyle(gras);
+// Compare les résultats de la première tentative aux notes du carnet EnCompass et affiche celles qui seraient ajoutées ou
+// modifiées. Les résultats sont convertis de l'échelle de leur évaluation au total du travail. Les notes ne sont envoyées
+// que si l'envoi est confirmé, et les notes EnCompass sans résultat correspondant ne sont jamais effacées. L'aperçu est
+// affiché quelle que soit la verbosité.
+pub fn envoyer_carnet_encompass(conn: &Connection, id_session: i64, portail: &encompass::Portail, confirmer: bool) -> Result<()> {
+    let groupes = groupes_encompass(conn, id_session)?;
+    let client = ilc_encompass::connecter(portail)?;
+    envoyer_carnet(conn, &client, id_session, &groupes, confirmer)
+}
+
+fn envoyer_carnet(conn: &Connection, client: &encompass::ClientEncompass, id_session: i64, groupes: &[(i32, String)], confirmer: bool) -> Result<()> {
+    let carnets = ilc_encompass::obtenir_carnets(client, groupes)?;
+
+    let mut envois = Vec::new();
+    for ((id_groupe, carnet), (_, codes)) in carnets.iter().zip(groupes) {
+        let mut notes = Vec::new();
+        for (id_cours, code) in &cours_groupe(conn, id_session, *id_groupe)? {
+            for Association { id_item, chemin, min, max, travail } in associer_travaux(conn, *id_cours, code, carnet)? {
+                let mut stmt = conn.prepare("
+                    SELECT élève.id_encompass, élève.prénom || ' ' || élève.nom, COALESCE(r.résultat, r.résultat_auto)
+                        FROM évaluation_résultat AS r
+                        JOIN élève ON élève.id = r.id_élève
+                        JOIN inscription ON inscription.id_élève = élève.id AND inscription.id_cours = ?2
+                        WHERE r.id_item = ?1 AND r.id_reprise IS NULL AND élève.id_encompass IS NOT NULL
+                        ORDER BY élève.nom, élève.prénom;
+                ")?;
+                let mut rangées = stmt.query(params![id_item, id_cours])?;
+                while let Some(r) = rangées.next()? {
+                    let (id_élève, nom_élève, résultat): (i32, String, f64) = (r.get(0)?, r.get(1)?, r.get(2)?);
+
+                    // Un élève absent du carnet n'est pas inscrit au groupe EnCompass.
+                    let actuelle = match carnet.notes.iter().find(|n| n.id_élève == id_élève && n.id_travail == travail.id) {
+                        Some(n) => n.note.map(|pourcentage| arrondir(pourcentage * travail.total / 100.0)),
+                        None => continue
+                    };
+                    let note = arrondir((résultat - min) / (max - min) * travail.total);
+                    match actuelle {
+                        Some(actuelle) if actuelle == note => continue,
+                        Some(actuelle) => println!("~ {} {} → {}, {}: {} → {} / {}", code, chemin, travail.nom, nom_élève, actuelle, note, travail.total),
+                        None => println!("+ {} {} → {}, {}: {} / {}", code, chemin, travail.nom, nom_élève, note, travail.total)
+                    }
+                    notes.push((travail.id, id_élève, note));
+                }
+            }
+        }
+
+        if !notes.is_empty() {
+            envois.push((*id_groupe, codes, notes));
+        }
+    }
+
+    let n_notes: usize = envois.iter().map(|(_, _, notes)| notes.len()).sum();
+    if n_notes == 0 {
+        info!("Le carnet de notes EnCompass est à jour.");
+    } else if !confirmer {
+        println!("{} notes seraient envoyées; aucune n'a été envoyée. Utilisez --confirmer pour les envoyer.", n_notes);
+    } else {
+        for (id_groupe, codes, notes) in &envois {
+            ilc_encompass::envoyer_notes(client, *id_groupe, codes, notes)?;
+        }
+        info!("Notes envoyées: {}", n_notes);
+    }
+
+    Ok(())
+}
+
+// Les absences et les retards importés remplacent ceux des cours associés au groupe EnCompass, qui fait foi. Les élèves
+// sont associés selon leur identifiant EnCompass.
+pub fn importer_absences_encompass(conn: &mut Connection, id_session: i64, portail: &encompass::Portail) -> Result<()> {
+    let groupes = groupes_encompass(conn, id_session)?;
+    let client = ilc_encompass::connecter(portail)?;
+    let absences = ilc_encompass::obtenir_absences(&client, &groupes)?;
+
+    let tx = conn.transaction()?;
+    let (mut n_absences, mut n_retards, mut n_ignorées) = (0, 0, 0);
+    for (id_groupe, absences_groupe) in &absences {
+        for (id_cours, code) in &cours_groupe(&tx, id_session, *id_groupe)? {
+            tx.execute("DELETE FROM assiduité WHERE id_cours = ?1", [id_cours])?;
+
+            for absence in absences_groupe {
+                let n = tx.execute("
+                    INSERT OR IGNORE INTO assiduité(id_élève, id_cours, date, période, retard)
+                        SELECT élève.id, ?2, ?3, ?4, ?5
+                            FROM élève
+                            JOIN inscription ON inscription.id_élève = élève.id
+                            WHERE élève.id_encompass = ?1 AND inscription.id_cours = ?2;
+                ", params![absence.id_élève, id_cours, absence.date.to_string(), absence.période, absence.retard])?;
+                match (n, absence.retard) {
+                    (0, _) => n_ignorées += 1,
+                    (_, false) => n_absences += 1,
+                    (_, true) => n_retards += 1
+                }
+            }
+            debug!("{} absences={}", code, absences_groupe.len());
+        }
+    }
+    tx.commit()?;
+
+    // Un élève d'un groupe partagé entre plusieurs cours n'est inscrit qu'à l'un d'eux.
+    debug!("absences ignorées={}", n_ignorées);
+    info!("Absences: {}, retards: {}", n_absences, n_retards);
+
+    Ok(())
+}
+
+// Le NISO est un renseignement personnel; il ne paraît dans la feuille « Élèves » que sur demande (option --niso).
+pub fn exporter_contacts_classeur(conn: &Connection, id_session: i64, étiquettes: &[String], niso: bool) -> Result<()> {
+    filtrer_élèves(conn, étiquettes)?;
+    let (mut wb, défaut_ref, gras_ref) = nouveau_classeur();
 
     let mut f_élèves = Sheet::new_with_name("Élèves");
-    vec!["Cours", "Prénom", "Nom"]
+    let colonnes: Vec<usize> = (0..11).filter(|&i| niso || i != 8).collect();
+    let titres = ["Cours", "Prénom", "Nom", "Majeur", "Étiquettes", "Niveau", "Crédits", "Courriel", "NISO", "Foyer", "Conseiller"];
+    colonnes
         .iter()
         .enumerate()
-        .for_each(|(i, titre)| f_élèves.set_styled_value(0, i as u32, *titre, &gras_ref));
+        .for_each(|(j, &i)| f_élèves.set_styled_value(0, j as u32, titres[i], &gras_ref));
 
     let mut f_tout = Sheet::new_with_name("Contacts");
-    vec!["Cours", "Prénom", "Nom", "Contact", "Relation", "Priorité", "Courriel", "Domicile", "Travail", "Cellulaire"]
+    ["Cours", "Prénom", "Nom", "Contact", "Relation", "Priorité", "Courriel", "Domicile", "Travail", "Cellulaire"]
         .iter()
         .enumerate()
         .for_each(|(i, titre)| f_tout.set_styled_value(0, i as u32, *titre, &gras_ref));
 
     let mut f_courriels = Sheet::new_with_name("Courriels");
-    vec!["Cours", "Prénom", "Nom", "Contact", "Relation", "Priorité", "Courriel"]
+    ["Cours", "Prénom", "Nom", "Contact", "Relation", "Priorité", "Courriel"]
         .iter()
         .enumerate()
         .for_each(|(i, titre)| f_courriels.set_styled_value(0, i as u32, *titre, &gras_ref));
 
     let mut f_téléphones = Sheet::new_with_name("Téléphones");
-    vec!["Cours", "Prénom", "Nom", "Contact", "Relation", "Priorité", "Domicile", "Travail", "Cellulaire"]
+    ["Cours", "Prénom", "Nom", "Contact", "Relation", "Priorité", "Domicile", "Travail", "Cellulaire"]
         .iter()
         .enumerate()
         .for_each(|(i, titre)| f_téléphones.set_styled_value(0, i as u32, *titre, &gras_ref));
 
+    let mut f_tuteurs = Sheet::new_with_name("Tuteurs");
+    ["Contact", "Élèves", "Courriel", "Domicile", "Travail", "Cellulaire"]
+        .iter()
+        .enumerate()
+        .for_each(|(i, titre)| f_tuteurs.set_styled_value(0, i as u32, *titre, &gras_ref));
+
     let mut stmt = conn.prepare("
-        SELECT cours.code, élève.prénom, élève.nom
+        SELECT cours.code, élève.prénom, élève.nom, CASE WHEN élève_statut.majeur THEN 'oui' ELSE 'non' END,
+            COALESCE((SELECT GROUP_CONCAT(nom, ', ') FROM élève_étiquette_nom WHERE id_élève = élève.id), ''),
+            COALESCE(CAST(élève.niveau AS TEXT), ''), COALESCE(PRINTF('%g', élève.crédits), ''),
+            COALESCE(élève.courriel, ''), COALESCE(élève.niso, ''), COALESCE(élève.foyer, ''), COALESCE(élève.conseiller, '')
             FROM élève
-            LEFT JOIN cours ON élève.id_cours = cours.id
+            JOIN élève_statut ON élève_statut.id_élève = élève.id
+            JOIN inscription ON inscription.id_élève = élève.id
+            LEFT JOIN cours ON cours.id = inscription.id_cours
+            WHERE cours.id_session = ?1
+                AND élève.id IN (SELECT id_élève FROM élève_filtré)
             ORDER BY cours.code, élève.prénom, élève.nom;")?;
-    let mut req = stmt.query([])?;
+    let mut req = stmt.query([id_session])?;
     let mut ligne = 1;
-    while let Ok(Some(r)) = req.next() {
-        for i in 0..3 {
-            f_élèves.set_styled_value(ligne, i, r.get::<_, String>(i as usize).unwrap(), &défaut_ref);
+    while let Some(r) = req.next()? {
+        for (j, &i) in colonnes.iter().enumerate() {
+            f_élèves.set_styled_value(ligne, j as u32, r.get::<_, String>(i)?, &défaut_ref);
         }
         ligne += 1;
     }
+    debug!("feuille=Élèves rangées={}", ligne - 1);
 
     let mut stmt = conn.prepare("
         SELECT cours.code, é.prénom, é.nom, COALESCE(c.nom_complet, ''), COALESCE(c.relation, ''), COALESCE(CAST(c.ordre as text), ''), COALESCE(i1.coordonnée, ''), COALESCE(i2.coordonnée, ''), COALESCE(i3.coordonnée, ''), COALESCE(i4.coordonnée, '')
             FROM élève as é
-            LEFT JOIN cours ON cours.id = é.id_cours
-            LEFT JOIN élève_contact AS c ON c.id_élève = é.id
-            LEFT JOIN élève_contact_item AS i1 ON i1.id_contact = c.id AND i1.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Courriel')
-            LEFT JOIN élève_contact_item AS i2 ON i2.id_contact = c.id AND i2.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au domicile')
-            LEFT JOIN élève_contact_item AS i3 ON i3.id_contact = c.id AND i3.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au travail')
-            LEFT JOIN élève_contact_item AS i4 ON i4.id_contact = c.id AND i4.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone cellulaire')
-            WHERE c.correspondance = 1
+            JOIN inscription ON inscription.id_élève = é.id
+            LEFT JOIN cours ON cours.id = inscription.id_cours
+            JOIN élève_contact_autorisé AS c ON c.id_élève = é.id
+            LEFT JOIN élève_contact_item_effectif AS i1 ON i1.id_contact = c.id AND i1.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Courriel')
+            LEFT JOIN élève_contact_item_effectif AS i2 ON i2.id_contact = c.id AND i2.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au domicile')
+            LEFT JOIN élève_contact_item_effectif AS i3 ON i3.id_contact = c.id AND i3.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au travail')
+            LEFT JOIN élève_contact_item_effectif AS i4 ON i4.id_contact = c.id AND i4.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone cellulaire')
+            WHERE cours.id_session = ?1
+                AND é.id IN (SELECT id_élève FROM élève_filtré)
             ORDER BY cours.code, é.prénom, é.nom, c.ordre, c.nom_complet;")?;
-    let mut req = stmt.query([])?;
+    let mut req = stmt.query([id_session])?;
     let mut ligne = 1;
-    while let Ok(Some(r)) = req.next() {
+    while let Some(r) = req.next()? {
         for i in 0..10 {
-            f_tout.set_styled_value(ligne, i, r.get::<_, String>(i as usize).unwrap(), &défaut_ref);
+            f_tout.set_styled_value(ligne, i, r.get::<_, String>(i as usize)?, &défaut_ref);
         }
         ligne += 1;
     }
+    debug!("feuille=Contacts rangées={}", ligne - 1);
 
     let mut stmt = conn.prepare("
         SELECT cours.code, é.prénom, é.nom, COALESCE(c.nom_complet, ''), COALESCE(c.relation, ''), COALESCE(CAST(c.ordre as text), ''), COALESCE(i.coordonnée, '')
             FROM élève as é
-            LEFT JOIN cours ON cours.id = é.id_cours
-            LEFT JOIN élève_contact AS c ON c.id_élève = é.id
-            LEFT JOIN élève_contact_item AS i ON i.id_contact = c.id
+            JOIN inscription ON inscription.id_élève = é.id
+            LEFT JOIN cours ON cours.id = inscription.id_cours
+            JOIN élève_contact_autorisé AS c ON c.id_élève = é.id
+            LEFT JOIN élève_contact_item_effectif AS i ON i.id_contact = c.id
             LEFT JOIN élève_contact_type AS t ON t.id = i.id_type
-            WHERE c.correspondance = 1 AND t.type = 'Courriel'
+            WHERE t.type = 'Courriel' AND cours.id_session = ?1
+                AND é.id IN (SELECT id_élève FROM élève_filtré)
             ORDER BY cours.code, é.prénom, é.nom, c.ordre, c.nom_complet;")?;
-    let mut req = stmt.query([])?;
+    let mut req = stmt.query([id_session])?;
     let mut ligne = 1;
-    while let Ok(Some(r)) = req.next() {
+    while let Some(r) = req.next()? {
         for i in 0..7 {
-            f_courriels.set_styled_value(ligne, i, r.get::<_, String>(i as usize).unwrap(), &défaut_ref);
+            f_courriels.set_styled_value(ligne, i, r.get::<_, String>(i as usize)?, &défaut_ref);
         }
         ligne += 1;
     }
+    debug!("feuille=Courriels rangées={}", ligne - 1);
 
     let mut stmt = conn.prepare("
         SELECT cours.code, é.prénom, é.nom, COALESCE(c.nom_complet, ''), COALESCE(c.relation, ''), COALESCE(CAST(c.ordre as text), ''), COALESCE(i1.coordonnée, ''), COALESCE(i2.coordonnée, ''), COALESCE(i3.coordonnée, '')
             FROM élève as é
-            LEFT JOIN cours ON cours.id = é.id_cours
-            LEFT JOIN élève_contact AS c ON c.id_élève = é.id
-            LEFT JOIN élève_contact_item AS i1 ON i1.id_contact = c.id AND i1.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au domicile')
-            LEFT JOIN élève_contact_item AS i2 ON i2.id_contact = c.id AND i2.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au travail')
-            LEFT JOIN élève_contact_item AS i3 ON i3.id_contact = c.id AND i3.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone cellulaire')
-            WHERE c.correspondance = 1
+            JOIN inscription ON inscription.id_élève = é.id
+            LEFT JOIN cours ON cours.id = inscription.id_cours
+            JOIN élève_contact_autorisé AS c ON c.id_élève = é.id
+            LEFT JOIN élève_contact_item_effectif AS i1 ON i1.id_contact = c.id AND i1.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au domicile')
+            LEFT JOIN élève_contact_item_effectif AS i2 ON i2.id_contact = c.id AND i2.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone au travail')
+            LEFT JOIN élève_contact_item_effectif AS i3 ON i3.id_contact = c.id AND i3.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Téléphone cellulaire')
+            WHERE cours.id_session = ?1
+                AND é.id IN (SELECT id_élève FROM élève_filtré)
             ORDER BY cours.code, é.prénom, é.nom, c.ordre, c.nom_complet;")?;
-    let mut req = stmt.query([])?;
+    let mut req = stmt.query([id_session])?;
     let mut ligne = 1;
-    while let Ok(Some(r)) = req.next() {
+    while let Some(r) = req.next()? {
         for i in 0..9 {
-            f_téléphones.set_styled_value(ligne, i, r.get::<_, String>(i as usize).unwrap(), &défaut_ref);
+            f_téléphones.set_styled_value(ligne, i, r.get::<_, String>(i as usize)?, &défaut_ref);
+        }
+        ligne += 1;
+    }
+    debug!("feuille=Téléphones rangées={}", ligne - 1);
+
+    let mut stmt = conn.prepare("
+        WITH
+            t_élève AS (
+                SELECT DISTINCT tc.id_tuteur, é.prénom || ' ' || é.nom || ' (' || cours.code || ')' AS élève
+                    FROM tuteur_contact AS tc
+                    JOIN élève_contact_autorisé AS c ON c.id = tc.id_contact
+                    JOIN élève AS é ON é.id = c.id_élève
+                    JOIN inscription ON inscription.id_élève = é.id
+                    LEFT JOIN cours ON cours.id = inscription.id_cours
+                    WHERE cours.id_session = ?1
+                        AND é.id IN (SELECT id_élève FROM élève_filtré)
+                    ORDER BY 2
+            ),
+            t_coordonnée AS (
+                SELECT DISTINCT tc.id_tuteur, t.type, i.coordonnée
+                    FROM tuteur_contact AS tc
+                    JOIN élève_contact_autorisé AS c ON c.id = tc.id_contact
+                    JOIN élève AS é ON é.id = c.id_élève
+                    JOIN inscription ON inscription.id_élève = é.id
+                    JOIN cours ON cours.id = inscription.id_cours
+                    JOIN élève_contact_item_effectif AS i ON i.id_contact = c.id
+                    JOIN élève_contact_type AS t ON t.id = i.id_type
+                    WHERE cours.id_session = ?1
+                        AND é.id IN (SELECT id_élève FROM élève_filtré)
+            )
+        SELECT t.nom_complet,
+            (SELECT GROUP_CONCAT(élève, ', ') FROM t_élève WHERE id_tuteur = t.id),
+            COALESCE((SELECT GROUP_CONCAT(coordonnée, ', ') FROM t_coordonnée WHERE id_tuteur = t.id AND type = 'Courriel'), ''),
+            COALESCE((SELECT GROUP_CONCAT(coordonnée, ', ') FROM t_coordonnée WHERE id_tuteur = t.id AND type = 'Téléphone au domicile'), ''),
+            COALESCE((SELECT GROUP_CONCAT(coordonnée, ', ') FROM t_coordonnée WHERE id_tuteur = t.id AND type = 'Téléphone au travail'), ''),
+            COALESCE((SELECT GROUP_CONCAT(coordonnée, ', ') FROM t_coordonnée WHERE id_tuteur = t.id AND type = 'Téléphone cellulaire'), '')
+            FROM tuteur AS t
+            WHERE EXISTS (SELECT 1 FROM t_élève WHERE id_tuteur = t.id)
+            ORDER BY t.nom_complet;")?;
+    let mut req = stmt.query([id_session])?;
+    let mut ligne = 1;
+    while let Some(r) = req.next()? {
+        for i in 0..6 {
+            f_tuteurs.set_styled_value(ligne, i, r.get::<_, String>(i as usize)?, &défaut_ref);
         }
         ligne += 1;
     }
+    debug!("feuille=Tuteurs rangées={}", ligne - 1);
 
     wb.push_sheet(f_élèves);
     wb.push_sheet(f_tout);
     wb.push_sheet(f_courriels);
     wb.push_sheet(f_téléphones);
+    wb.push_sheet(f_tuteurs);
 
     let date = Local::now().format("%Y-%m-%d_%H-%M-%S");
     write_ods(&mut wb, format!["élèves_{}.ods", date])?;
 
     Ok(())
 }
+
+
+pub fn exporter_publipostage(conn: &Connection, id_session: i64, étiquettes: &[String]) -> Result<()> {
+    filtrer_élèves(conn, étiquettes)?;
+    let mut stmt = conn.prepare("
+        SELECT cours.code, é.id, é.prénom, é.nom, i.normalisée, a.absences, a.retards, COALESCE(é.courriel, ''), COALESCE(é.conseiller, '')
+            FROM élève AS é
+            JOIN inscription ON inscription.id_élève = é.id
+            LEFT JOIN cours ON cours.id = inscription.id_cours
+            JOIN élève_assiduité AS a ON a.id_élève = é.id AND a.id_cours = inscription.id_cours
+            LEFT JOIN élève_contact_autorisé AS c ON c.id_élève = é.id
+            LEFT JOIN élève_contact_item_effectif AS i ON i.id_contact = c.id AND i.valide = 1
+                AND i.id_type = (SELECT id FROM élève_contact_type WHERE type = 'Courriel')
+            WHERE cours.id_session = ?1
+                AND é.id IN (SELECT id_élève FROM élève_filtré)
+            ORDER BY cours.code, é.prénom, é.nom, é.id, c.ordre, c.nom_complet;")?;
+    let mut req = stmt.query([id_session])?;
+
+    struct Destinataire {
+        code: String,
+        id_élève: i64,
+        prénom: String,
+        nom: String,
+        courriels: Vec<String>,
+        absences: i64,
+        retards: i64,
+        courriel_élève: String,
+        conseiller: String
+    }
+
+    let mut élèves: Vec<Destinataire> = Vec::new();
+    while let Some(r) = req.next()? {
+        let code: String = r.get(0)?;
+        let id_élève: i64 = r.get(1)?;
+        let courriel: Option<String> = r.get(4)?;
+
+        if élèves.last().map(|é| (&é.code, é.id_élève)) != Some((&code, id_élève)) {
+            élèves.push(Destinataire {
+                code,
+                id_élève,
+                prénom: r.get(2)?,
+                nom: r.get(3)?,
+                courriels: Vec::new(),
+                absences: r.get(5)?,
+                retards: r.get(6)?,
+                courriel_élève: r.get(7)?,
+                conseiller: r.get(8)?
+            });
+        }
+
+        if let Some(courriel) = courriel.filter(|c| !c.is_empty()) {
+            let courriels = &mut élèves.last_mut().unwrap().courriels;
+            if !courriels.contains(&courriel) {
+                courriels.push(courriel);
+            }
+        }
+    }
+
+    // Les tuteurs de plusieurs élèves d'un même cours ou groupe n'y paraissent qu'une fois.
+    let mut cci: Vec<(String, Vec<String>)> = Vec::new();
+    for Destinataire { code, courriels, .. } in &élèves {
+        let mut groupes = vec![code.clone()];
+        if let Some((base, _)) = code.split_once('-') {
+            groupes.push(base.to_string());
+        }
+
+        for groupe in groupes {
+            let idx = match cci.iter().position(|(g, _)| *g == groupe) {
+                Some(idx) => idx,
+                None => {
+                    cci.push((groupe, Vec::new()));
+                    cci.len() - 1
+                }
+            };
+
+            let destinataires = &mut cci[idx].1;
+            for courriel in courriels {
+                if !destinataires.contains(courriel) {
+                    destinataires.push(courriel.clone());
+                }
+            }
+        }
+    }
+    cci.sort_by(|a, b| a.0.cmp(&b.0));
+    debug!("publipostage élèves={} groupes_cci={}", élèves.len(), cci.len());
+
+    let date = Local::now().format("%Y-%m-%d_%H-%M-%S");
+
+    // Les colonnes ajoutées depuis suivent les courriels, pour ne pas déplacer les champs des modèles de publipostage existants.
+    let n_colonnes = élèves.iter().map(|é| é.courriels.len()).max().unwrap_or(0).max(1);
+    let mut csv = csv::Writer::from_path(format!["publipostage_{}.csv", date])?;
+    let mut titres = vec!["Cours".to_string(), "Prénom".to_string(), "Nom".to_string()];
+    titres.extend((1..=n_colonnes).map(|i| format!("Courriel {}", i)));
+    titres.extend(["Absences", "Retards", "Courriel de l'élève", "Conseiller"].iter().map(|t| t.to_string()));
+    csv.write_record(&titres)?;
+    for é in &élèves {
+        let mut rangée = vec![é.code.clone(), é.prénom.clone(), é.nom.clone()];
+        rangée.extend(é.courriels.iter().cloned());
+        rangée.resize(3 + n_colonnes, String::new());
+        rangée.extend([é.absences.to_string(), é.retards.to_string(), é.courriel_élève.clone(), é.conseiller.clone()]);
+        csv.write_record(&rangée)?;
+    }
+    csv.flush().map_err(csv::Error::from)?;
+
+    let mut csv = csv::Writer::from_path(format!["cci_{}.csv", date])?;
+    csv.write_record(["Cours", "Destinataires", "CCI"])?;
+    for (groupe, destinataires) in &cci {
+        csv.write_record(&[groupe.clone(), destinataires.len().to_string(), destinataires.join(";")])?;
+    }
+    csv.flush().map_err(csv::Error::from)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spreadsheet_ods::style::units::Length;
+    use crate::encompass::substitut::{Réponse, Substitut};
+
+    // Le cours MHF4U-01 est associé au groupe 42 du carnet de démonstration. Léa (5001) a 90 au test 1 (15/20 dans
+    // EnCompass) et 80 au projet (EX); Noah (5002) a 87,5 au test 1, déjà inscrit (17,5/20).
+    fn base_carnet() -> Connection {
+        let conn = bd::ouvrir(None).unwrap();
+        conn.execute_batch("
+            INSERT INTO année_scolaire(id, nom, début, fin) VALUES (100, '2030-2031', '2030-09-01', '2031-06-30');
+            INSERT INTO session(id, id_année, semestre) VALUES (100, 100, 1);
+            INSERT INTO cours(id, code, id_session, id_groupe) VALUES (1, 'MHF4U-01', 100, 42);
+            INSERT INTO élève(id, id_encompass, prénom, nom) VALUES (1, 5001, 'Léa', 'Tremblay'), (2, 5002, 'Noah', 'Gagnon');
+            INSERT INTO inscription(id_élève, id_cours) VALUES (1, 1), (2, 1);
+            INSERT INTO échelle(id, nom, précision, min, max) VALUES (1, 'Pourcentage', 1, 0, 100);
+            INSERT INTO évaluation_item(id, nom, id_cours, id_parent, indice, id_échelle) VALUES
+                (1, 'Connaissance et compréhension', 1, NULL, 0, 1);
+            INSERT INTO évaluation_item(id, nom, id_cours, id_parent, indice) VALUES
+                (2, 'Test 1', 1, 1, 0),
+                (3, 'Projet & présentation', 1, 1, 1);
+            INSERT INTO évaluation_résultat(id_item, id_élève, résultat) VALUES (2, 1, 90), (3, 1, 80), (2, 2, 87.5);
+        ").unwrap();
+        conn
+    }
+
+    fn substitut() -> Substitut {
+        Substitut::démarrer(vec![
+            ("/auth/login.do", Réponse::redirection("/portal/home.do")),
+            ("/gb/class/42/gradebook.do", Réponse::page(include_str!("../fixtures/encompass/gradebook.html"))),
+            ("/gb/class/42/saveMarks.do", Réponse::redirection("/portal/gb/class/42/gradebook.do"))
+        ])
+    }
+
+    // Deux élèves du cours MHF4U-01 se nomment Léa Tremblay; la colonne EnCompass les distingue. Le composant Q2 est ajouté
+    // à une évaluation existante dont le composant Q0 a été retiré du classeur.
+    fn classeur_notes(fichier: &Path, encompass: bool) {
+        let mut feuille = Sheet::new_with_name("MHF4U");
+        feuille.set_value(0, 5, "Test 1");
+        feuille.set_value(1, 5, "Connaissance");
+        feuille.set_value(2, 5, "Q1");
+        feuille.set_value(2, 6, "Q2");
+        feuille.set_value(0, 7, "EnCompass");
+        let élèves: [(&str, &str, f64, Option<f64>, f64); 3] = [
+            ("Léa", "Tremblay", 80., Some(70.), 5001.),
+            ("Léa", "Tremblay", 60., None, 5002.),
+            ("Noah", "Gagnon", 90., Some(85.), 5003.)
+        ];
+        for (i, (prénom, nom, q1, q2, id)) in élèves.iter().enumerate() {
+            let ligne = i as u32 + 3;
+            feuille.set_value(ligne, 0, *prénom);
+            feuille.set_value(ligne, 2, *nom);
+            feuille.set_value(ligne, 3, *prénom);
+            feuille.set_value(ligne, 4, "MHF4U-01");
+            feuille.set_value(ligne, 5, *q1);
+            if let Some(q2) = q2 {
+                feuille.set_value(ligne, 6, *q2);
+            }
+            if encompass && *prénom == "Léa" {
+                feuille.set_value(ligne, 7, *id);
+            }
+        }
+
+        // Les dimensions utilisées de la feuille sont celles de ses en-têtes de lignes et de colonnes.
+        for ligne in 0..6 {
+            feuille.set_row_height(ligne, Length::Cm(0.5));
+        }
+        for colonne in 0..8 {
+            feuille.set_col_width(colonne, Length::Cm(2.));
+        }
+        let mut wb = spreadsheet_ods::WorkBook::new();
+        wb.push_sheet(feuille);
+        write_ods(&mut wb, fichier).unwrap();
+    }
+
+    #[test]
+    fn importation_classeur() {
+        let fichier = std::env::temp_dir().join(format!("contacteur_évaluations_{}.ods", std::process::id()));
+        let mut conn = bd::ouvrir(None).unwrap();
+        conn.execute_batch("
+            INSERT INTO année_scolaire(id, nom, début, fin) VALUES (100, '2030-2031', '2030-09-01', '2031-06-30');
+            INSERT INTO session(id, id_année, semestre) VALUES (100, 100, 1);
+            INSERT INTO cours(id, code, id_session) VALUES (1, 'MHF4U-01', 100);
+            INSERT INTO élève(id, id_encompass, prénom, nom) VALUES (1, 5001, 'Léa', 'Tremblay'), (2, 5002, 'Léa', 'Tremblay'), (3, 5003, 'Noah', 'Gagnon');
+            INSERT INTO inscription(id_élève, id_cours) VALUES (1, 1), (2, 1), (3, 1);
+            INSERT INTO évaluation_item(id, nom, id_cours, id_parent, indice) VALUES
+                (1, 'Test 1', 1, NULL, 0), (2, 'Connaissance', 1, 1, 0), (3, 'Q0', 1, 2, 0);
+            INSERT INTO évaluation_résultat(id_item, id_élève, résultat) VALUES (3, 1, 50);
+        ").unwrap();
+
+        classeur_notes(&fichier, false);
+        let erreur = importer_notes_classeur(&mut conn, 100, fichier.to_str().unwrap());
+        assert!(matches!(erreur, Err(Erreur::Classeur { ligne: Some(4), .. })));
+
+        classeur_notes(&fichier, true);
+        importer_notes_classeur(&mut conn, 100, fichier.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&fichier).unwrap();
+
+        let résultats: Vec<(String, i64, f64)> = conn
+            .prepare("
+                SELECT item.nom, r.id_élève, r.résultat
+                    FROM évaluation_résultat AS r
+                    JOIN évaluation_item AS item ON item.id = r.id_item
+                    ORDER BY item.nom, r.id_élève;
+            ").unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(résultats, vec![
+            ("Q0".into(), 1, 50.),
+            ("Q1".into(), 1, 80.), ("Q1".into(), 2, 60.), ("Q1".into(), 3, 90.),
+            ("Q2".into(), 1, 70.), ("Q2".into(), 3, 85.)
+        ]);
+
+        let vus: HashSet<i64> = conn
+            .prepare("SELECT id FROM évaluation_item WHERE nom <> 'Q0';").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(items_orphelins(&conn, 1, &vus).unwrap(), vec!["Test 1 / Connaissance / Q0".to_string()]);
+        assert_eq!(items_orphelins(&conn, 1, &HashSet::new()).unwrap(), vec!["Test 1".to_string()]);
+    }
+
+    // Marie Tremblay est la mère de Léa et de Noah; Luc Tremblay partage son courriel, mais pas son nom.
+    #[test]
+    fn coordonnée_tuteur() {
+        let mut conn = bd::ouvrir(None).unwrap();
+        conn.execute_batch("
+            INSERT INTO élève(id, prénom, nom) VALUES (1, 'Léa', 'Tremblay'), (2, 'Noah', 'Tremblay');
+            INSERT INTO élève_contact(id, id_élève, nom_complet, correspondance, automatique) VALUES
+                (10, 1, 'Marie Tremblay', 1, 1), (11, 2, 'marie tremblay ', 1, 1), (12, 2, 'Luc Tremblay', 1, 1);
+            INSERT INTO élève_contact_item(id_contact, id_type, coordonnée, automatique, normalisée) VALUES
+                (10, 1, 'Marie@Exemple.ca', 1, 'marie@exemple.ca'), (11, 1, 'marie@exemple.ca', 1, 'marie@exemple.ca'),
+                (12, 1, 'marie@exemple.ca', 1, 'marie@exemple.ca');
+        ").unwrap();
+        regrouper_tuteurs(&mut conn).unwrap();
+
+        let tuteurs: Vec<i64> = conn
+            .prepare("SELECT id_tuteur FROM tuteur_contact ORDER BY id_contact;").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(tuteurs[0], tuteurs[1]);
+        assert_ne!(tuteurs[0], tuteurs[2]);
+
+        remplacer_coordonnée(&conn, 11, "Courriel", "marie.tremblay@exemple.ca").unwrap();
+        let courriels: Vec<(i64, String)> = conn
+            .prepare("SELECT id_contact, coordonnée FROM élève_contact_item_effectif ORDER BY id_contact;").unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(courriels, vec![
+            (10, "marie.tremblay@exemple.ca".to_string()),
+            (11, "marie.tremblay@exemple.ca".to_string()),
+            (12, "marie@exemple.ca".to_string())
+        ]);
+    }
+
+    #[test]
+    fn envoi_carnet_aperçu() {
+        let conn = base_carnet();
+        let substitut = substitut();
+        let client = encompass::ClientEncompass::new(&substitut.portail, "u", "p").unwrap();
+
+        envoyer_carnet(&conn, &client, 100, &groupes_encompass(&conn, 100).unwrap(), false).unwrap();
+        let chemins: Vec<String> = substitut.requêtes().into_iter().map(|r| format!("{} {}", r.méthode, r.chemin)).collect();
+        assert_eq!(chemins, vec!["POST /portal/auth/login.do", "GET /portal/gb/class/42/gradebook.do"]);
+    }
+
+    #[test]
+    fn envoi_carnet_confirmé() {
+        let conn = base_carnet();
+        let substitut = substitut();
+        let client = encompass::ClientEncompass::new(&substitut.portail, "u", "p").unwrap();
+
+        envoyer_carnet(&conn, &client, 100, &groupes_encompass(&conn, 100).unwrap(), true).unwrap();
+        assert_eq!(substitut.envois("/saveMarks.do"), vec!["mark_102_5001=30&mark_101_5001=18"]);
+    }
+}
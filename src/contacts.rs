@@ -1,22 +1,39 @@
-mod bd;
-mod classeur;
-mod connecteurs;
-mod encompass;
-mod erreur;
-mod ilc;
-mod ilc_encompass;
+use std::process::ExitCode;
+use log::info;
+use contacteur::{bd, erreur, etiquettes, ilc, journal};
+use contacteur::erreur::{Erreur, Result};
+use contacteur::connecteurs::{exporter_contacts_classeur, exporter_publipostage, importer_contacts_manuels, importer_encompass};
+use contacteur::rapports::exporter_rapport_contacts;
+use contacteur::portail::résoudre_portail;
+use contacteur::sessions::résoudre_session;
 
-use crate::erreur::Result;
-use crate::connecteurs::{exporter_contacts_classeur, importer_encompass};
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    erreur::terminer(journal::initialiser(&mut args).and_then(|()| exécuter(args)))
+}
+
+fn exécuter(mut args: Vec<String>) -> Result<()> {
+    let session = ilc::extraire_option(&mut args, "--session")?;
+    let langue = ilc::extraire_option(&mut args, "--langue")?;
+    let hôte = ilc::extraire_option(&mut args, "--hôte")?;
+    let étiquettes = etiquettes::lire_étiquettes(&ilc::extraire_option(&mut args, "--étiquette")?.unwrap_or_default());
+    let niso = ilc::extraire_drapeau(&mut args, "--niso");
+    if let Some(arg) = args.first() {
+        return Err(Erreur::Arguments(format!("L'argument {} est inconnu.", arg)));
+    }
 
-fn main() -> Result<()> {
     let mut conn = bd::ouvrir(None)?;
+    let id_session = résoudre_session(&conn, session.as_deref())?;
+    let portail = résoudre_portail(&conn, langue.as_deref(), hôte.as_deref())?;
 
-    importer_encompass(&mut conn)?;
+    importer_encompass(&mut conn, id_session, &portail)?;
+    importer_contacts_manuels(&mut conn, id_session)?;
 
-    println!("Exportation des données à un fichier...");
-    exporter_contacts_classeur(&conn)?;
-    println!("Exportation réussie!");
+    info!("Exportation des données à un fichier...");
+    exporter_contacts_classeur(&conn, id_session, &étiquettes, niso)?;
+    exporter_publipostage(&conn, id_session, &étiquettes)?;
+    exporter_rapport_contacts(&conn, id_session, &étiquettes)?;
+    info!("Exportation réussie!");
 
     Ok(())
 }
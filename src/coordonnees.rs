@@ -0,0 +1,98 @@
+use std::sync::OnceLock;
+use regex::Regex;
+
+// Format E.164 (+1XXXXXXXXXX), suivi du poste à la manière de RFC 3966 (;ext=XXX).
+pub fn normaliser_téléphone(téléphone: &str) -> Option<String> {
+    static R_POSTE: OnceLock<Regex> = OnceLock::new();
+    let r_poste = R_POSTE.get_or_init(|| Regex::new(r"(?i)^(.*?)\s*(?:poste|ext\.?|x|#)\s*([0-9]+)$").unwrap());
+
+    let téléphone = téléphone.trim();
+    let (numéro, poste) = match r_poste.captures(téléphone) {
+        Some(c) => (c.get(1).unwrap().as_str(), Some(c[2].to_string())),
+        None => (téléphone, None)
+    };
+
+    if numéro.chars().any(|c| c.is_alphabetic()) {
+        return None;
+    }
+
+    let chiffres: String = numéro.chars().filter(|c| c.is_ascii_digit()).collect();
+    let chiffres = match chiffres.len() {
+        10 => &chiffres[..],
+        11 if chiffres.starts_with('1') => &chiffres[1..],
+        _ => return None
+    };
+
+    // Ni l'indicatif régional ni le central ne commencent par 0 ou 1 en Amérique du Nord.
+    if chiffres.starts_with(['0', '1']) || chiffres[3..].starts_with(['0', '1']) {
+        return None;
+    }
+
+    Some(match poste {
+        Some(poste) => format!("+1{};ext={}", chiffres, poste),
+        None => format!("+1{}", chiffres)
+    })
+}
+
+pub fn normaliser_courriel(courriel: &str) -> String {
+    courriel.trim().to_lowercase()
+}
+
+pub fn courriel_valide(courriel: &str) -> bool {
+    static R_COURRIEL: OnceLock<Regex> = OnceLock::new();
+    let r_courriel = R_COURRIEL.get_or_init(|| {
+        Regex::new(r"^[a-z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-z0-9](?:[a-z0-9-]*[a-z0-9])?(?:\.[a-z0-9](?:[a-z0-9-]*[a-z0-9])?)*\.[a-z]{2,}$").unwrap()
+    });
+
+    r_courriel.is_match(courriel)
+        && !courriel.starts_with('.')
+        && !courriel.contains("..")
+        && !courriel.contains(".@")
+}
+
+// Retourne la coordonnée normalisée, s'il est possible de la normaliser, et sa validité.
+pub fn normaliser(type_item: &str, coordonnée: &str) -> (Option<String>, bool) {
+    if type_item == "Courriel" {
+        let courriel = normaliser_courriel(coordonnée);
+        let valide = courriel_valide(&courriel);
+        (Some(courriel), valide)
+    } else {
+        match normaliser_téléphone(coordonnée) {
+            Some(téléphone) => (Some(téléphone), true),
+            None => (None, false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn téléphones() {
+        assert_eq!(normaliser_téléphone("(613) 555-1234").as_deref(), Some("+16135551234"));
+        assert_eq!(normaliser_téléphone("(613) 555-1234 poste 22").as_deref(), Some("+16135551234;ext=22"));
+        assert_eq!(normaliser_téléphone("613.555.1234 ext. 7").as_deref(), Some("+16135551234;ext=7"));
+        assert_eq!(normaliser_téléphone("1-613-555-1234").as_deref(), Some("+16135551234"));
+        assert_eq!(normaliser_téléphone(" +1 613 555 1234 x 305 ").as_deref(), Some("+16135551234;ext=305"));
+
+        assert_eq!(normaliser_téléphone("555-1234"), None);
+        assert_eq!(normaliser_téléphone("613-555-123"), None);
+        assert_eq!(normaliser_téléphone("2-613-555-1234"), None);
+        assert_eq!(normaliser_téléphone("613-055-1234"), None);
+        assert_eq!(normaliser_téléphone("Appeler le bureau"), None);
+    }
+
+    #[test]
+    fn courriels() {
+        assert_eq!(normaliser("Courriel", " Marie.Tremblay@Exemple.CA "), (Some("marie.tremblay@exemple.ca".to_string()), true));
+        assert!(courriel_valide("marie+ecole@exemple.on.ca"));
+
+        for courriel in ["marie@", "@exemple.ca", "marie@exemple", "marie@@exemple.ca", ".marie@exemple.ca", "marie..t@exemple.ca",
+            "marie.@exemple.ca", "marie tremblay@exemple.ca", "marie@-exemple.ca", "marie@exemple.c"] {
+            assert!(!courriel_valide(courriel), "{}", courriel);
+        }
+        assert_eq!(normaliser("Courriel", "marie@"), (Some("marie@".to_string()), false));
+        assert_eq!(normaliser("Téléphone au domicile", "555-1234"), (None, false));
+    }
+}
@@ -1,77 +1,252 @@
-mod bd;
-mod classeur;
-mod connecteurs;
-mod encompass;
-mod erreur;
-mod foret;
-mod ilc;
-mod ilc_encompass;
-
-use std::collections::HashMap;
-use regex::Regex;
-use rusqlite::params;
-use spreadsheet_ods::{read_ods, Value, xmltree::XmlContent};
-use crate::erreur::{Erreur, Result};
-use crate::foret::Forêt;
-use crate::connecteurs::{exporter_contacts_classeur, importer_encompass, importer_notes_classeur};
-
-fn main() -> Result<()> {
-    let mut conn = bd::ouvrir(Some("contacteur.db3"))?;
+use contacteur::{bd, erreur, ilc, journal};
+use contacteur::erreur::Result;
+use contacteur::etiquettes::{étiqueter, lire_étiquettes, lister_étiquettes, obtenir_étiquette, retirer_étiquette, supprimer_étiquette, trouver_élève};
+use std::path::Path;
+use std::process::ExitCode;
+use log::info;
+use contacteur::evaluations::{
+    calculer_totaux, charger_évaluations, copier_structure, exporter_structure, importer_structure, lire_politique, lire_structure,
+    obtenir_notes, écrire_structure
+};
+use contacteur::connecteurs::{envoyer_carnet_encompass, importer_absences_encompass, importer_carnet_encompass, importer_notes_classeur};
+use contacteur::rapports::exporter_rapport_absences;
+use contacteur::portail::{choisir_portail, résoudre_portail};
+use contacteur::risques::{afficher_risques, détecter_risques, exporter_risques, lire_règles, lister_appels, noter_appel};
+use contacteur::statistiques::{afficher_statistiques, calculer_statistiques, exporter_statistiques};
+use contacteur::sessions::{choisir_session, lire_référence_cours, lire_session, lister_sessions, nom_session, obtenir_session, résoudre_session, session_courante};
 
-    let sauter = false;
-    if sauter {
-        importer_encompass(&mut conn)?;
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    erreur::terminer(journal::initialiser(&mut args).and_then(|()| exécuter(args)))
+}
 
-        println!("Exportation des données à un fichier...");
-        exporter_contacts_classeur(&conn)?;
-        println!("Exportation réussie!");
+fn exécuter(mut args: Vec<String>) -> Result<()> {
+    let session = ilc::extraire_option(&mut args, "--session")?;
+    let langue = ilc::extraire_option(&mut args, "--langue")?;
+    let hôte = ilc::extraire_option(&mut args, "--hôte")?;
+    let règles = ilc::extraire_option(&mut args, "--règles")?.unwrap_or_else(|| "risques.toml".to_string());
+    let étiquettes = lire_étiquettes(&ilc::extraire_option(&mut args, "--étiquette")?.unwrap_or_default());
+    let politique = lire_politique(&ilc::extraire_option(&mut args, "--notes-manquantes")?.unwrap_or_else(|| "incomplet".to_string()))?;
 
-        println!("Importation des notes d'évaluation...");
-        importer_notes_classeur(&mut conn)?;
-        println!("Importation réussie!");
-    }
+    let mut conn = bd::ouvrir(Some("contacteur.db3"))?;
+    let id_session = résoudre_session(&conn, session.as_deref())?;
+    let portail = résoudre_portail(&conn, langue.as_deref(), hôte.as_deref())?;
 
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    match args[..] {
+        ["session"] => {
+            let id_courante = session_courante(&conn)?;
+            for (id, nom, n_cours) in lister_sessions(&conn)? {
+                let marque = if id == id_courante { "*" } else { " " };
+                println!("{} {} ({} cours)", marque, nom, n_cours);
+            }
+            return Ok(());
+        },
+        ["session", "choisir", session] => {
+            let (année, semestre) = lire_session(session)?;
+            let id_session = obtenir_session(&conn, année, semestre)?;
+            choisir_session(&conn, Some(id_session))?;
+            println!("La session courante est {}.", nom_session(&conn, id_session)?);
+            return Ok(());
+        },
+        ["session", "automatique"] => {
+            choisir_session(&conn, None)?;
+            println!("La session courante est {}.", nom_session(&conn, session_courante(&conn)?)?);
+            return Ok(());
+        },
+        ["portail"] => {
+            println!("Portail EnCompass: {} ({})", portail.hôte, portail.langue.code());
+            return Ok(());
+        },
+        ["portail", "choisir", langue] | ["portail", "choisir", langue, _] => {
+            let portail = choisir_portail(&conn, langue, args.get(3).copied())?;
+            println!("Le portail EnCompass est {} ({}).", portail.hôte, portail.langue.code());
+            return Ok(());
+        },
+        ["notes", "importer"] | ["notes", "importer", _] => {
+            info!("Importation des notes d'évaluation...");
+            importer_notes_classeur(&mut conn, id_session, args.get(2).copied().unwrap_or("évaluations.ods"))?;
+            info!("Importation réussie!");
+            return Ok(());
+        },
+        ["carnet", "importer"] => {
+            info!("Importation du carnet de notes EnCompass...");
+            importer_carnet_encompass(&mut conn, id_session, &portail)?;
+            info!("Importation réussie!");
+            return Ok(());
+        },
+        ["carnet", "envoyer"] | ["carnet", "envoyer", "--confirmer"] => {
+            envoyer_carnet_encompass(&conn, id_session, &portail, args.len() == 3)?;
+            return Ok(());
+        },
+        ["absences", "importer"] => {
+            info!("Importation des absences EnCompass...");
+            importer_absences_encompass(&mut conn, id_session, &portail)?;
+            info!("Importation réussie!");
+            return Ok(());
+        },
+        ["absences", "rapport"] => {
+            info!("Exportation du rapport d'absences...");
+            exporter_rapport_absences(&conn, id_session, &étiquettes)?;
+            info!("Exportation réussie!");
+            return Ok(());
+        },
+        ["statistiques"] => {
+            afficher_statistiques(&calculer_statistiques(&conn, id_session, &étiquettes)?);
+            return Ok(());
+        },
+        ["statistiques", "rapport"] => {
+            info!("Exportation des statistiques...");
+            exporter_statistiques(&calculer_statistiques(&conn, id_session, &étiquettes)?)?;
+            info!("Exportation réussie!");
+            return Ok(());
+        },
+        ["risques"] => {
+            let règles = lire_règles(Path::new(&règles))?;
+            afficher_risques(&détecter_risques(&conn, id_session, &règles, &étiquettes)?);
+            return Ok(());
+        },
+        ["risques", "rapport"] => {
+            let règles = lire_règles(Path::new(&règles))?;
+            info!("Exportation des élèves à risque...");
+            exporter_risques(&détecter_risques(&conn, id_session, &règles, &étiquettes)?)?;
+            info!("Exportation réussie!");
+            return Ok(());
+        },
+        ["appel"] => {
+            for (date, élève, réussi, note) in lister_appels(&conn, id_session)? {
+                println!("{} {} ({}) {}", date, élève, if réussi { "réussi" } else { "échoué" }, note);
+            }
+            return Ok(());
+        },
+        ["appel", résultat, élève] | ["appel", résultat, élève, _] if résultat == "réussi" || résultat == "échoué" => {
+            let id_élève = trouver_élève(&conn, élève, id_session)?;
+            noter_appel(&conn, id_élève, résultat == "réussi", args.get(3).copied())?;
+            println!("L'appel aux tuteurs de {} est noté.", élève);
+            return Ok(());
+        },
+        ["structure", "exporter", cours, fichier] => {
+            let (code, id_session) = lire_référence_cours(&conn, cours, id_session)?;
+            let structure = exporter_structure(&conn, &code, id_session)?;
+            écrire_structure(Path::new(fichier), &structure)?;
+            println!("Structure du cours {} exportée vers {}.", cours, fichier);
+            return Ok(());
+        },
+        ["structure", "importer", fichier] => {
+            let structure = lire_structure(Path::new(fichier))?;
+            importer_structure(&mut conn, &structure, id_session)?;
+            println!("Structure du cours {} importée de {}.", structure.cours, fichier);
+            return Ok(());
+        },
+        ["structure", "copier", source, destination] => {
+            let (code_source, id_session_source) = lire_référence_cours(&conn, source, id_session)?;
+            let (code_destination, id_session_destination) = lire_référence_cours(&conn, destination, id_session)?;
+            copier_structure(&mut conn, (&code_source, id_session_source), (&code_destination, id_session_destination))?;
+            println!("Structure du cours {} copiée vers le cours {}.", source, destination);
+            return Ok(());
+        },
+        ["étiquette"] => {
+            for (nom, n_élèves) in lister_étiquettes(&conn)? {
+                println!("{} ({} élèves)", nom, n_élèves);
+            }
+            return Ok(());
+        },
+        ["étiquette", "créer", nom] => {
+            obtenir_étiquette(&conn, nom)?;
+            println!("L'étiquette {} est créée.", nom);
+            return Ok(());
+        },
+        ["étiquette", "supprimer", nom] => {
+            supprimer_étiquette(&mut conn, nom)?;
+            println!("L'étiquette {} est supprimée.", nom);
+            return Ok(());
+        },
+        ["étiquette", "ajouter", nom, ref élèves @ ..] if !élèves.is_empty() => {
+            for élève in élèves {
+                let id_élève = trouver_élève(&conn, élève, id_session)?;
+                if étiqueter(&conn, id_élève, nom)? {
+                    println!("+ {}: {}", élève, nom);
+                }
+            }
+            return Ok(());
+        },
+        ["étiquette", "retirer", nom, ref élèves @ ..] if !élèves.is_empty() => {
+            for élève in élèves {
+                let id_élève = trouver_élève(&conn, élève, id_session)?;
+                if retirer_étiquette(&conn, id_élève, nom)? {
+                    println!("- {}: {}", élève, nom);
+                }
+            }
+            return Ok(());
+        },
+        [] => (),
+        _ => {
+            println!("Utilisation:");
+            println!("    dev-experimental [-q | -v | -vv] [--journal <fichier>] [--session <année>/<semestre>] [--étiquette <étiquette>,...]");
+            println!("        [--langue <fr ou en>] [--hôte <hôte>] [--notes-manquantes <ignorer, zéro ou incomplet>]");
+            println!("    dev-experimental session");
+            println!("    dev-experimental session choisir <année>/<semestre>");
+            println!("    dev-experimental session automatique");
+            println!("    dev-experimental portail");
+            println!("    dev-experimental portail choisir <fr ou en> [<hôte>]");
+            println!("    dev-experimental notes importer [<fichier .ods>]");
+            println!("    dev-experimental carnet importer");
+            println!("    dev-experimental carnet envoyer [--confirmer]");
+            println!("    dev-experimental absences importer");
+            println!("    dev-experimental absences rapport");
+            println!("    dev-experimental statistiques [rapport]");
+            println!("    dev-experimental [--règles <fichier .toml ou .json>] risques [rapport]");
+            println!("    dev-experimental appel");
+            println!("    dev-experimental appel <réussi ou échoué> <« Prénom Nom »> [<note>]");
+            println!("    dev-experimental structure exporter <cours> <fichier .json ou .toml>");
+            println!("    dev-experimental structure importer <fichier .json ou .toml>");
+            println!("    dev-experimental structure copier <cours source> <cours destination>");
+            println!("    dev-experimental étiquette");
+            println!("    dev-experimental étiquette créer <étiquette>");
+            println!("    dev-experimental étiquette supprimer <étiquette>");
+            println!("    dev-experimental étiquette ajouter <étiquette> <« Prénom Nom »>...");
+            println!("    dev-experimental étiquette retirer <étiquette> <« Prénom Nom »>...");
+            println!("Un cours est désigné par son code dans la session choisie ou par son code et sa session (p. ex. MPM2D@2025-2026/2).");
+            return Ok(());
+        }
+    }
 
     // TODO: exporter au format PDF/html-email approprié
-    return Ok(());
-
     let mut stmt = conn.prepare("
-        SELECT id, code FROM cours ORDER BY code;
-    ").unwrap();
-    let ids_cours = stmt.query_map([], |r| Ok((
-        r.get_unwrap::<_, u32>(0),
-        r.get_unwrap::<_, String>(1)
-    )))?.filter_map(|id| id.ok());
-    for (id, code) in ids_cours {
-        let mut évaluations = Forêt::new();
-        let mut items = HashMap::new();
+        SELECT id, code FROM cours WHERE id_session = ?1 ORDER BY code;
+    ")?;
+    let ids_cours: Vec<(i64, String)> = stmt
+        .query_map([id_session], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id_cours, code) in ids_cours {
+        let évaluations = charger_évaluations(&conn, id_cours)?;
+        let racines = évaluations.racines();
         println!("---{}", code);
+
         let mut stmt = conn.prepare("
-            WITH RECURSIVE
-                arbre(id, nom, id_parent, niveau, indice) AS (
-                    SELECT id, nom, id_parent, 0, indice
-                        FROM évaluation_item
-                        WHERE id_cours = ?1 AND id_parent IS NULL
-                    UNION ALL
-                    SELECT éi.id, éi.nom, éi.id_parent, arbre.niveau+1, éi.indice
-                        FROM évaluation_item AS éi
-                        JOIN arbre ON éi.id_parent = arbre.id
-                        ORDER BY 3 DESC, éi.indice
-                )
-            SELECT id, nom, id_parent FROM arbre;
-        ").unwrap();
-        let mut rangées = stmt.query([id])?;
-        while let Some(r) = rangées.next()? {
-            println!("{}", r.get_unwrap::<_, String>(1));
-            let id: u32 = r.get_unwrap(0);
-            let id_parent: Option<u32> = r.get_unwrap(2);
-            let id_loc = match id_parent {
-                Some(id_parent) => évaluations.ajouter_descendant(items[&id_parent], id)?,
-                None => évaluations.créer(id)
-            };
-            items.insert(id, id_loc);
+            SELECT élève.id, élève.prénom, élève.nom
+                FROM élève
+                JOIN inscription ON inscription.id_élève = élève.id
+                WHERE inscription.id_cours = ?1
+                ORDER BY élève.prénom, élève.nom;
+        ")?;
+        let élèves: Vec<(i64, String, String)> = stmt
+            .query_map([id_cours], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        for (id_élève, prénom, nom) in élèves {
+            let notes = obtenir_notes(&conn, id_élève)?;
+            let totaux = calculer_totaux(&évaluations, &notes, politique)?;
+
+            let mut résultats = Vec::new();
+            for racine in &racines {
+                let item = évaluations.val(*racine)?;
+                résultats.push(match totaux[&item.id].valeur {
+                    Some(v) => format!("{}: {:.2}", item.nom, v),
+                    None => format!("{}: incomplet", item.nom)
+                });
+            }
+            println!("{} {}: {}", prénom, nom, résultats.join(", "));
         }
-        println!("Fini!");
     }
 
     Ok(())
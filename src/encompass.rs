@@ -1,23 +1,138 @@
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Instant;
 use chrono::naive::NaiveDate;
 use html_escape::decode_html_entities;
+use log::{debug, warn};
 use percent_encoding::percent_decode_str;
 use regex::Regex;
 use reqwest::{blocking::Client, redirect::Policy, StatusCode};
 use crate::erreur::{Erreur, Result};
 
 pub struct ClientEncompass {
-    client: Client
+    client: Client,
+    racine: String,
+    langue: Langue
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Langue {
+    Français,
+    Anglais
+}
+
+// Les libellés sont des fragments d'expressions régulières. Les mois sont reconnus par le début de leur nom, avec ou sans
+// point (p. ex. « sept. », « sep » ou « septembre »), et les états d'assiduité par une partie de leur nom en minuscules.
+struct Libellés {
+    naissance: &'static str,
+    nom: &'static str,
+    domicile: &'static str,
+    travail: &'static str,
+    cellulaire: &'static str,
+    courriel: &'static str,
+    correspondance: &'static str,
+    priorité: &'static str,
+    relation_inconnue: &'static str,
+    courriel_élève: &'static str,
+    niso: &'static str,
+    foyer: &'static str,
+    année: &'static str,
+    conseiller: &'static str,
+    absence: &'static [&'static str],
+    retard: &'static [&'static str],
+    niveau: &'static [&'static str],
+    crédits: &'static [&'static str],
+    pei: &'static [&'static str],
+    alf: &'static [&'static str],
+    mois: [&'static str; 12]
+}
+
+const LIBELLÉS_FRANÇAIS: Libellés = Libellés {
+    naissance: "Date de naissance",
+    nom: "Nom",
+    domicile: "Domicile",
+    travail: "Travail",
+    cellulaire: "Cellulaire",
+    courriel: "Courriel",
+    correspondance: "Correspondance",
+    priorité: "Priorité de fermeture",
+    relation_inconnue: "Inconnu",
+    courriel_élève: "(?:Courriel de l(?:'|&#39;|&#x27;|’)élève|Courriel scolaire)",
+    niso: "(?:NISO|Numéro d(?:'|&#39;|&#x27;|’)immatriculation scolaire de l(?:'|&#39;|&#x27;|’)Ontario)",
+    foyer: "(?:Salle[- ]foyer|Foyer)",
+    année: "(?:Niveau|Année d(?:'|&#39;|&#x27;|’)études)",
+    conseiller: "(?:Conseill(?:er|ère)(?: en orientation| d(?:'|&#39;|&#x27;|’)orientation)?|Orientation)",
+    absence: &["absen"],
+    retard: &["retard"],
+    niveau: &["niveau", "année"],
+    crédits: &["crédit"],
+    pei: &["pei"],
+    alf: &["alf", "pana"],
+    mois: ["janv", "févr", "mars", "avr", "mai", "juin", "juil", "août", "sep", "oct", "nov", "déc"]
+};
+
+const LIBELLÉS_ANGLAIS: Libellés = Libellés {
+    naissance: "(?:Date of Birth|Birth Date|Birthdate)",
+    nom: "Name",
+    domicile: "Home",
+    travail: "Work",
+    cellulaire: "(?:Cell|Mobile)",
+    courriel: "E-?mail",
+    correspondance: "Correspondence",
+    priorité: "(?:Closure|Closing) Priority",
+    relation_inconnue: "Unknown",
+    courriel_élève: "(?:Student E-?mail|School E-?mail)",
+    niso: "(?:OEN|Ontario Education Number)",
+    foyer: "(?:Homeroom|Home Room)",
+    année: "Grade",
+    conseiller: "(?:Guidance Counsel+or|Guidance|Counsel+or)",
+    absence: &["absen"],
+    retard: &["late", "tardy"],
+    niveau: &["grade"],
+    crédits: &["credit"],
+    pei: &["iep"],
+    alf: &["ell", "esl"],
+    mois: ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"]
+};
+
+impl Langue {
+    fn libellés(self) -> &'static Libellés {
+        match self {
+            Langue::Français => &LIBELLÉS_FRANÇAIS,
+            Langue::Anglais => &LIBELLÉS_ANGLAIS
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Langue::Français => "fr",
+            Langue::Anglais => "en"
+        }
+    }
+
+    pub fn hôte_défaut(self) -> &'static str {
+        match self {
+            Langue::Français => "french.compassforsuccess.ca",
+            Langue::Anglais => "www.compassforsuccess.ca"
+        }
+    }
+}
+
+// L'hôte permet d'utiliser le portail d'un autre conseil scolaire qui utilise la même plateforme.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Portail {
+    pub hôte: String,
+    pub langue: Langue
 }
 
 pub struct Groupe {
-    id: i32,
+    pub id: i32,
     pub code: String
 }
 
 #[derive(Clone)]
 pub struct Cours {
-    id_groupe: i32,
+    pub id_groupe: i32,
     pub code: String,
     pub élèves: Vec<Élève>
 }
@@ -25,7 +140,7 @@ pub struct Cours {
 impl Cours {
     fn new<S: Into<String>>(id_groupe: i32, code: S) -> Self {
         Self {
-            id_groupe: id_groupe,
+            id_groupe,
             code: code.into(),
             élèves: Vec::new()
         }
@@ -34,23 +149,119 @@ impl Cours {
 
 #[derive(Clone)]
 pub struct Élève {
-    id: i32,
+    pub id: i32,
     pub prénom: String,
     pub nom: String,
     pub naissance: Option<NaiveDate>,
+    pub majeur: bool,
+    pub suivi: Suivi,
+    pub fiche: Fiche,
     pub contacts: Vec<Contact>
 }
 
 impl Élève {
     fn new<S: Into<String>>(id: i32, prénom: S, nom: S) -> Self {
         Self {
-            id: id,
+            id,
             prénom: prénom.into(),
             nom: nom.into(),
             naissance: None,
+            majeur: false,
+            suivi: Suivi::default(),
+            fiche: Fiche::default(),
             contacts: Vec::new()
         }
     }
+
+    pub fn compléter(&mut self, données: DonnéesÉlève) {
+        self.naissance = données.naissance;
+        self.majeur = données.majeur;
+        self.fiche = données.fiche;
+        self.contacts = données.contacts;
+    }
+}
+
+// Les champs de la fiche de l'élève (page gbInfo); un champ absent ou vide est None. Le NISO (numéro d'immatriculation
+// scolaire de l'Ontario, OEN en anglais) est conservé sans séparateurs.
+#[derive(Clone, Default)]
+pub struct Fiche {
+    pub courriel: Option<String>,
+    pub niso: Option<String>,
+    pub foyer: Option<String>,
+    pub niveau: Option<u32>,
+    pub conseiller: Option<String>
+}
+
+#[derive(Clone)]
+pub struct DonnéesÉlève {
+    pub naissance: Option<NaiveDate>,
+    pub majeur: bool,
+    pub fiche: Fiche,
+    pub contacts: Vec<Contact>
+}
+
+// Les indicateurs du tableau de suivi de la réussite, sous le titre de leur colonne, et ceux qui sont reconnus. Un
+// indicateur absent du tableau est None.
+#[derive(Clone, Default)]
+pub struct Suivi {
+    pub niveau: Option<u32>,
+    pub crédits: Option<f64>,
+    pub pei: Option<bool>,
+    pub alf: Option<bool>,
+    pub indicateurs: Vec<(String, String)>
+}
+
+// Les identifiants ne sont envoyés sans chiffrement qu'à un serveur local, p. ex. un substitut du portail lors d'essais.
+pub fn est_local(nom: &str) -> bool {
+    let autorité = nom.split('/').next().unwrap_or_default();
+    let hôte = match autorité.strip_prefix('[') {
+        Some(reste) => reste.split(']').next().unwrap_or_default(),
+        None => autorité.split(':').next().unwrap_or_default()
+    };
+    ["localhost", "127.0.0.1", "::1"].contains(&hôte.to_lowercase().as_str())
+}
+
+// Le bloc de l'élève majeur commence par la mention « Student is 18 », suivie des contacts auxquels il consent, en gras (ou
+// NONE), et se termine avec l'élément qui la contient.
+fn bloc_majeur(page: &str) -> Option<&str> {
+    let bloc = &page[page.find("Student is 18")?..];
+    let fin = ["</div>", "</table>"].iter().filter_map(|f| bloc.find(f)).min().unwrap_or(bloc.len());
+    Some(&bloc[..fin])
+}
+
+// Le carnet de notes d'un groupe: les catégories et leur pondération, les travaux et les notes de chaque élève, en
+// pourcentage. Une note absente (vide ou p. ex. « EX ») est conservée comme None.
+pub struct Carnet {
+    pub catégories: Vec<Catégorie>,
+    pub travaux: Vec<Travail>,
+    pub notes: Vec<Note>
+}
+
+pub struct Catégorie {
+    pub nom: String,
+    pub pondération: f64
+}
+
+pub struct Travail {
+    pub id: i32,
+    pub nom: String,
+    pub catégorie: String,
+    pub total: f64,
+    pub pondération: f64
+}
+
+pub struct Note {
+    pub id_élève: i32,
+    pub id_travail: i32,
+    pub note: Option<f64>
+}
+
+// Une absence ou un retard d'un élève à une période d'un cours.
+pub struct Absence {
+    pub id_élève: i32,
+    pub date: NaiveDate,
+    pub période: u32,
+    pub retard: bool
 }
 
 #[derive(Clone)]
@@ -62,140 +273,808 @@ pub struct Contact {
     pub tel_cellulaire: Option<String>,
     pub courriel: Option<String>,
     pub correspondance: bool,
+    pub consentement: bool,
     pub ordre: Option<u32>
 }
 
 impl ClientEncompass {
-    pub fn new(utilisateur: &str, mot_de_passe: &str) -> Result<Self> {
-        let client = match Client::builder()
+    pub fn new(portail: &Portail, utilisateur: &str, mot_de_passe: &str) -> Result<Self> {
+        let client = Client::builder()
             .cookie_store(true)
             .redirect(Policy::none())
-            .build() {
-            Ok(client) => client,
-            Err(e) => return Err(Erreur::Requête(e))
-        };
+            .build()?;
 
+        // Ni le mot de passe ni les témoins de session ne sont journalisés.
+        let racine = match portail.hôte.strip_prefix("http://") {
+            Some(nom) if est_local(nom) => format!("{}/portal", portail.hôte),
+            Some(_) => return Err(Erreur::Arguments(format!("L'hôte {} doit utiliser https:// (http:// est réservé à un serveur local).", portail.hôte))),
+            None => format!("https://{}/portal", portail.hôte)
+        };
+        let url = format!("{}/auth/login.do", racine);
+        let début = Instant::now();
         let res = client
-            .post("https://french.compassforsuccess.ca/portal/auth/login.do")
+            .post(&url)
             .form(&[
                 ("username", &utilisateur),
                 ("password", &mot_de_passe)
             ])
             .send()?;
+        debug!("POST {} état={} durée={} ms", url, res.status(), début.elapsed().as_millis());
 
         match res.status() {
-            StatusCode::FOUND => Ok(Self { client: client }),
+            StatusCode::FOUND => Ok(Self { client, racine, langue: portail.langue }),
             StatusCode::OK => Err(Erreur::IdentifiantsInvalides),
-            _ => Err(Erreur::ÉtatInconnu(res.status()))
+            état => Err(Erreur::ÉtatInconnu { url, état })
+        }
+    }
+
+    fn obtenir_page(&self, url: &str) -> Result<String> {
+        let début = Instant::now();
+        let res = self.client.get(url).send()?;
+        match res.status() {
+            StatusCode::OK => {
+                let page = res.text()?;
+                debug!("GET {} état=200 durée={} ms octets={}", url, début.elapsed().as_millis(), page.len());
+                Ok(page)
+            },
+            état => {
+                debug!("GET {} état={} durée={} ms", url, état, début.elapsed().as_millis());
+                Err(Erreur::ÉtatInconnu { url: url.to_string(), état })
+            }
         }
     }
 
     pub fn obtenir_groupes(&mut self) -> Result<Vec<Groupe>> {
-        let res = self.client
-            .get("https://french.compassforsuccess.ca/portal/class/search.do?text=")
-            .send();
+        let url = format!("{}/class/search.do?text=", self.racine);
+        let page = self.obtenir_page(&url)?;
 
         let r_classes = Regex::new(r"classID=([^&]+).+className=([^&]+)").unwrap();
-        match res {
-            Result::Ok(res) if res.status() == StatusCode::OK => {
-                let page = res.text().unwrap_or(String::new());
-                Ok(r_classes.captures_iter(&page).map(|c| Groupe {
-                    id: c[1].parse().unwrap(),
-                    code: percent_decode_str(&c[2]).decode_utf8_lossy().to_string()
-                }).collect())
+        let groupes = r_classes.captures_iter(&page).map(|c| {
+            Ok(Groupe {
+                id: lire_nombre(&url, "l'identifiant du groupe", &c[1])?,
+                code: percent_decode_str(&c[2]).decode_utf8_lossy().to_string()
+            })
+        }).collect::<Result<Vec<_>>>()?;
+        debug!("{} groupes={}", url, groupes.len());
+
+        Ok(groupes)
+    }
+
+    pub fn obtenir_élèves_groupe(&mut self, groupe: &Groupe) -> Result<Vec<Cours>> {
+        let url = format!("{}/studentsuccess/studentSuccessMonitoringTable.do?classId={}", self.racine, groupe.id);
+        let page = self.obtenir_page(&url)?;
+        lire_tableau_suivi(&url, &page, groupe.id, self.langue.libellés())
+    }
+
+    // Retourne la date de naissance, si l'élève est majeur, sa fiche et ses contacts. Les contacts d'un élève majeur ne
+    // sont pas retirés; ceux auxquels il a consenti sont marqués.
+    pub fn obtenir_données_élève(&self, élève: &Élève) -> Result<DonnéesÉlève> {
+        let url = format!("{}/gb/student/{}/gbInfo.do", self.racine, élève.id);
+        let page = self.obtenir_page(&url)?;
+
+        lire_données_élève(&url, &page, élève, self.langue.libellés())
+    }
+
+    // Les en-têtes du carnet décrivent les catégories (data-category-weight) et les travaux (data-assignment-id,
+    // data-category, data-out-of et data-weight); chaque rangée d'élève contient une cellule par travail.
+    pub fn obtenir_carnet(&self, id_groupe: i32) -> Result<Carnet> {
+        let url = format!("{}/gb/class/{}/gradebook.do", self.racine, id_groupe);
+        let page = self.obtenir_page(&url)?;
+        lire_carnet(&url, &page)
+    }
+
+
+    // Chaque rangée du registre décrit une période d'un élève (data-id, data-date et data-period) et son état
+    // (data-status). Les présences et les états inconnus sont ignorés.
+    pub fn obtenir_absences(&self, id_groupe: i32) -> Result<Vec<Absence>> {
+        let url = format!("{}/attendance/class/{}/attendance.do", self.racine, id_groupe);
+        let page = self.obtenir_page(&url)?;
+
+        lire_absences(&url, &page, self.langue.libellés())
+    }
+
+    // Le formulaire du carnet reçoit un champ mark_<travail>_<élève> par note, sur le total du travail. Le portail répond par
+    // une redirection vers le carnet, ou par le carnet lui-même.
+    pub fn envoyer_notes(&self, id_groupe: i32, notes: &[(i32, i32, f64)]) -> Result<()> {
+        let url = format!("{}/gb/class/{}/saveMarks.do", self.racine, id_groupe);
+        let champs: Vec<(String, String)> = notes
+            .iter()
+            .map(|(id_travail, id_élève, note)| (format!("mark_{}_{}", id_travail, id_élève), note.to_string()))
+            .collect();
+
+        let début = Instant::now();
+        let res = self.client.post(&url).form(&champs).send()?;
+        debug!("POST {} état={} durée={} ms notes={}", url, res.status(), début.elapsed().as_millis(), notes.len());
+
+        let redirection = res.headers().get(reqwest::header::LOCATION).and_then(|l| l.to_str().ok()).unwrap_or_default();
+        match res.status() {
+            StatusCode::FOUND if redirection.contains("login") => {
+                Err(Erreur::Page { url, message: "la session a expiré et les notes n'ont pas été enregistrées".into() })
             },
-            Result::Ok(res) => Err(Erreur::ÉtatInconnu(res.status())),
-            Result::Err(err) => Err(Erreur::Requête(err))
+            StatusCode::OK | StatusCode::FOUND => Ok(()),
+            état => Err(Erreur::ÉtatInconnu { url, état })
         }
     }
+}
 
-    pub fn obtenir_élèves_groupe(&mut self, groupe: &Groupe) -> Result<Vec<Cours>> {
-        let res = self.client
-            .get(format!("https://french.compassforsuccess.ca/portal/studentsuccess/studentSuccessMonitoringTable.do?classId={}", groupe.id))
-            .send();
-
-        let r_élèves = Regex::new(r#"(?s:<tr +data-id="([0-9]+)".+?([^>]+)</a>.+?<td.+?>([^<>]+).+?(?:<td.+?){5}([0-9]*)</td>.+?([^>]+)</td>)"#).unwrap();
-        match res {
-            Result::Ok(res) if res.status() == StatusCode::OK => {
-                let page = res.text().unwrap_or(String::new());
-
-                let mut élèves = HashMap::new();
-                r_élèves.captures_iter(&page).for_each(|c| {
-                    let mut code = decode_html_entities(&c[5]).to_string();
-                    if !c[4].is_empty() {
-                        code.push_str("-");
-                        code.push_str(&format!("{:0>2}", decode_html_entities(&c[4])));
-                    }
-                    
-                    élèves
-                        .entry(code.clone())
-                        .or_insert_with(|| Cours::new(groupe.id, code))
-                        .élèves
-                        .push(Élève::new(
-                            c[1].parse().unwrap(),
-                            decode_html_entities(&c[2]),
-                            decode_html_entities(&c[3])
-                        ))
-                });
-
-                Ok(élèves.values().cloned().collect())
+// La fiche de l'élève (page gbInfo): sa date de naissance, les champs de sa fiche et ses contacts.
+fn lire_données_élève(url: &str, page: &str, élève: &Élève, l: &Libellés) -> Result<DonnéesÉlève> {
+    let r_date = Regex::new(&format!(r#"(?s:<th>{}</th>\s+<td>.+?>([^<>]+)</span>)"#, l.naissance)).unwrap();
+    let r_majeur = Regex::new(r#"<STRONG>([^<>]+)</STRONG>"#).unwrap();
+    let r_contact = Regex::new(&format!(
+        r#"(?s:<th>{}.+?>([^<>]+)</span.+?([^<>]*)</span.+?{}.+?<td>([^<>]*).+?{}.+?<td>([^<>]*).+?{}.+?<td>([^<>]*).+?{}.+?>([^<>]*)</a>.+?{}.+?(green|red).+?{}.+?>([0-9]+)</td>)"#,
+        l.nom, l.domicile, l.travail, l.cellulaire, l.courriel, l.correspondance, l.priorité
+    )).unwrap();
+
+    let naissance = match r_date.captures(page) {
+        Some(c) => Some(lire_date(&c[1], &l.mois).ok_or_else(|| {
+            Erreur::Page { url: url.to_string(), message: format!("la date de naissance {} est invalide", c[1].trim()) }
+        })?),
+        None => None
+    };
+
+    let champ = |libellé: &str| {
+        let r_champ = Regex::new(&format!(r#"(?s:<th>\s*{}\s*:?\s*</th>\s*<td[^>]*>(.*?)</td>)"#, libellé)).unwrap();
+        r_champ.captures(page).map(|c| texte_cellule(&c[1])).filter(|t| !t.is_empty())
+    };
+    let niso = champ(l.niso).and_then(|niso| {
+        let chiffres: String = niso.chars().filter(char::is_ascii_digit).collect();
+        if chiffres.len() == 9 {
+            Some(chiffres)
+        } else {
+            warn!("Le NISO {} de {} {} est invalide (9 chiffres attendus).", niso, élève.prénom, élève.nom);
+            None
+        }
+    });
+    let fiche = Fiche {
+        courriel: champ(l.courriel_élève),
+        niso,
+        foyer: champ(l.foyer),
+        niveau: champ(l.année).and_then(|n| n.split(|c: char| !c.is_ascii_digit()).find(|n| !n.is_empty())?.parse().ok()),
+        conseiller: champ(l.conseiller)
+    };
+
+    let mut contacts = Vec::new();
+    for c in r_contact.captures_iter(page) {
+        contacts.push(Contact {
+            nom_complet: decode_html_entities(&c[1]).into(),
+            relation: if !c[2].is_empty() && &c[2] != "Unknown" && &c[2] != l.relation_inconnue { Some(decode_html_entities(&c[2]).into()) } else { None },
+            tel_domicile: if !c[3].is_empty() { Some(decode_html_entities(&c[3]).into()) } else { None },
+            tel_travail: if !c[4].is_empty() { Some(decode_html_entities(&c[4]).into()) } else { None },
+            tel_cellulaire: if !c[5].is_empty() { Some(decode_html_entities(&c[5]).into()) } else { None },
+            courriel: if !c[6].is_empty() { Some(decode_html_entities(&c[6]).into()) } else { None },
+            correspondance: &c[7] == "green",
+            consentement: false,
+            ordre: Some(lire_nombre(url, "la priorité du contact", &c[8])?)
+        });
+    }
+
+    // Seul le statut indiqué par EnCompass est retourné; la base de données reconnaît aussi un élève majeur selon sa date de
+    // naissance. Les noms en gras hors du bloc de l'élève majeur ne sont pas des consentements.
+    let bloc = bloc_majeur(page);
+    let majeur = bloc.is_some();
+    if let Some(bloc) = bloc {
+        let contacts_majeur: Vec<_> = r_majeur.captures_iter(bloc).filter_map(|c| {
+            if &c[1] == "NONE" {
+                None
+            } else {
+                Some(decode_html_entities(&c[1]).to_string())
+            }
+        }).collect();
+        for contact in &mut contacts {
+            contact.consentement = contacts_majeur.contains(&contact.nom_complet);
+        }
+    }
+
+    debug!("{} contacts={} date_naissance={} majeur={} niso={} conseiller={}", url, contacts.len(), naissance.is_some(), majeur,
+        fiche.niso.is_some(), fiche.conseiller.is_some());
+    Ok(DonnéesÉlève { naissance, majeur, fiche, contacts })
+}
+
+// Dans chaque rangée, le prénom est le premier lien et est suivi du nom, de quatre indicateurs, de la section et du code du
+// cours. Les cellules suivantes sont aussi des indicateurs, nommés selon la rangée d'en-tête du tableau; si elle n'a pas
+// autant de colonnes que la rangée de l'élève, les titres sont ignorés plutôt qu'attribués aux mauvaises cellules.
+fn lire_tableau_suivi(url: &str, page: &str, id_groupe: i32, l: &Libellés) -> Result<Vec<Cours>> {
+    let r_rangée = Regex::new(r#"(?s:<tr +data-id="([0-9]+)"[^>]*>(.*?)</tr>)"#).unwrap();
+    let r_cellule = Regex::new(r#"(?s:<td[^>]*>(.*?)</td>)"#).unwrap();
+
+    let titres = match r_rangée.find(page) {
+        Some(rangée) => titres_tableau(&page[..rangée.start()]),
+        None => Vec::new()
+    };
+    let mut titres_ignorés = false;
+    let mut élèves = HashMap::new();
+    for rangée in r_rangée.captures_iter(page) {
+        let cellules: Vec<&str> = r_cellule.captures_iter(&rangée[2]).map(|c| c.get(1).unwrap().as_str()).collect();
+        let p = match cellules.iter().position(|c| c.contains("</a>")) {
+            Some(p) if cellules.len() >= p + 8 => p,
+            _ => return Err(Erreur::Page { url: url.to_string(), message: format!("la rangée de l'élève {} est incomplète", &rangée[1]) })
+        };
+
+        let titres_rangée = if titres.len() == cellules.len() {
+            &titres[..]
+        } else {
+            if !titres_ignorés {
+                warn!(
+                    "L'en-tête du tableau de suivi {} a {} colonnes, mais la rangée de l'élève {} en a {}; les titres des indicateurs sont ignorés.",
+                    url, titres.len(), &rangée[1], cellules.len()
+                );
+                titres_ignorés = true;
+            }
+            &[]
+        };
+
+        let section = texte_cellule(cellules[p + 6]);
+        let mut code = texte_cellule(cellules[p + 7]);
+        if !section.is_empty() {
+            code.push_str(&format!("-{:0>2}", section));
+        }
+
+        let mut élève = Élève::new(
+            lire_nombre(url, "l'identifiant de l'élève", &rangée[1])?,
+            texte_cellule(cellules[p]),
+            texte_cellule(cellules[p + 1])
+        );
+        let indices = (p + 2..p + 6).chain(p + 8..cellules.len());
+        élève.suivi = lire_suivi(l, indices.map(|i| {
+            let titre = titres_rangée.get(i).filter(|t| !t.is_empty()).cloned().unwrap_or_else(|| format!("Colonne {}", i + 1));
+            (titre, cellules[i])
+        }));
+
+        élèves
+            .entry(code.clone())
+            .or_insert_with(|| Cours::new(id_groupe, code))
+            .élèves
+            .push(élève);
+    }
+
+    debug!("{} cours={} élèves={}", url, élèves.len(), élèves.values().map(|c| c.élèves.len()).sum::<usize>());
+    Ok(élèves.values().cloned().collect())
+}
+
+// Les titres de la dernière rangée d'en-tête du tableau qui précède la première rangée d'élève, répétés selon leur colspan.
+// Les en-têtes des autres tableaux de la page (p. ex. une légende) sont ignorés.
+fn titres_tableau(avant_rangées: &str) -> Vec<String> {
+    let r_rangée = Regex::new(r#"(?s:<tr[^>]*>(.*?)</tr>)"#).unwrap();
+    let r_entête = Regex::new(r#"(?s:<th([^>]*)>(.*?)</th>)"#).unwrap();
+
+    let tableau = &avant_rangées[avant_rangées.rfind("<table").unwrap_or(0)..];
+    let entête = match r_rangée.captures_iter(tableau).filter(|r| r[1].contains("<th")).last() {
+        Some(entête) => entête,
+        None => return Vec::new()
+    };
+
+    let mut titres = Vec::new();
+    for c in r_entête.captures_iter(&entête[1]) {
+        let colonnes = attribut(&c[1], "colspan").and_then(|n| n.parse().ok()).unwrap_or(1usize);
+        titres.extend(std::iter::repeat_n(texte_cellule(&c[2]), colonnes));
+    }
+
+    titres
+}
+
+// Les indicateurs reconnus selon le titre de leur colonne: le niveau (p. ex. « 10 » ou « 10e »), les crédits obtenus et les
+// drapeaux (p. ex. « Oui », « X » ou une icône).
+fn lire_suivi<'a, I: Iterator<Item = (String, &'a str)>>(l: &Libellés, colonnes: I) -> Suivi {
+    let r_nombre = Regex::new(r"[0-9]+(?:[.,][0-9]+)?").unwrap();
+
+    let mut suivi = Suivi::default();
+    for (titre, html) in colonnes {
+        let mut texte = texte_cellule(html);
+        if texte.is_empty() && html.contains("<img") {
+            texte = attribut(html, "alt").or_else(|| attribut(html, "title")).filter(|t| !t.is_empty()).unwrap_or_else(|| "✓".into());
+        }
+        let clé = titre.to_lowercase();
+        let reconnu = |libellés: &[&str]| libellés.iter().any(|l| clé.split(|c: char| !c.is_alphanumeric()).any(|mot| mot.starts_with(l)));
+        let nombre = r_nombre.find(&texte).and_then(|n| n.as_str().replace(',', ".").parse::<f64>().ok());
+        let drapeau = html.contains("<img")
+            || ["oui", "yes", "o", "y", "x", "✓", "✔", "1"].contains(&texte.to_lowercase().as_str());
+
+        if reconnu(l.niveau) {
+            suivi.niveau = nombre.map(|n| n as u32);
+        } else if reconnu(l.crédits) {
+            suivi.crédits = nombre;
+        } else if reconnu(l.pei) {
+            suivi.pei = Some(drapeau);
+        } else if reconnu(l.alf) {
+            suivi.alf = Some(drapeau);
+        }
+        suivi.indicateurs.push((titre, texte));
+    }
+
+    suivi
+}
+
+
+
+fn lire_carnet(url: &str, page: &str) -> Result<Carnet> {
+    let r_catégorie = Regex::new(r#"(?s:<th([^>]*\bdata-category-weight="[^"]*"[^>]*)>(.*?)</th>)"#).unwrap();
+    let r_travail = Regex::new(r#"(?s:<th([^>]*\bdata-assignment-id="[^"]*"[^>]*)>(.*?)</th>)"#).unwrap();
+    let r_rangée = Regex::new(r#"(?s:<tr[^>]*\bdata-id="([0-9]+)"[^>]*>(.*?)</tr>)"#).unwrap();
+    let r_note = Regex::new(r#"(?s:<td([^>]*\bdata-assignment-id="[^"]*"[^>]*)>(.*?)</td>)"#).unwrap();
+
+    let nombre = |balise: &str, nom: &str, description: &str| -> Result<Option<f64>> {
+        attribut(balise, nom).map(|texte| lire_nombre(url, description, &texte.replace(',', "."))).transpose()
+    };
+
+    let mut catégories = Vec::new();
+    for c in r_catégorie.captures_iter(page) {
+        catégories.push(Catégorie {
+            nom: texte_cellule(&c[2]),
+            pondération: nombre(&c[1], "data-category-weight", "la pondération de la catégorie")?.unwrap_or(1.0)
+        });
+    }
+
+    let mut travaux = Vec::new();
+    for c in r_travail.captures_iter(page) {
+        let nom = texte_cellule(&c[2]);
+        let total = nombre(&c[1], "data-out-of", "le total du travail")?.unwrap_or(100.0);
+        if total <= 0.0 {
+            return Err(Erreur::Page { url: url.to_string(), message: format!("le total du travail {} est nul", nom) });
+        }
+
+        travaux.push(Travail {
+            id: lire_nombre(url, "l'identifiant du travail", &attribut(&c[1], "data-assignment-id").unwrap_or_default())?,
+            catégorie: attribut(&c[1], "data-category").filter(|c| !c.is_empty()).unwrap_or_else(|| nom.clone()),
+            nom,
+            total,
+            pondération: nombre(&c[1], "data-weight", "la pondération du travail")?.unwrap_or(1.0)
+        });
+    }
+
+    // Les travaux d'une catégorie sans en-tête ont la pondération par défaut.
+    for travail in &travaux {
+        if !catégories.iter().any(|c| c.nom == travail.catégorie) {
+            catégories.push(Catégorie { nom: travail.catégorie.clone(), pondération: 1.0 });
+        }
+    }
+
+    let mut notes = Vec::new();
+    for rangée in r_rangée.captures_iter(page) {
+        let id_élève = lire_nombre(url, "l'identifiant de l'élève", &rangée[1])?;
+        for c in r_note.captures_iter(&rangée[2]) {
+            let id_travail: i32 = lire_nombre(url, "l'identifiant du travail", &attribut(&c[1], "data-assignment-id").unwrap_or_default())?;
+            let travail = travaux.iter().find(|t| t.id == id_travail).ok_or_else(|| {
+                Erreur::Page { url: url.to_string(), message: format!("le travail {} est inconnu", id_travail) }
+            })?;
+            notes.push(Note {
+                id_élève,
+                id_travail,
+                note: texte_cellule(&c[2]).replace(',', ".").parse::<f64>().ok().map(|n| n / travail.total * 100.0)
+            });
+        }
+    }
+
+    debug!("{} catégories={} travaux={} notes={}", url, catégories.len(), travaux.len(), notes.iter().filter(|n| n.note.is_some()).count());
+    Ok(Carnet { catégories, travaux, notes })
+}
+
+fn lire_absences(url: &str, page: &str, l: &Libellés) -> Result<Vec<Absence>> {
+    let r_rangée = Regex::new(r#"<tr([^>]*\bdata-date="[^"]*"[^>]*)>"#).unwrap();
+
+    let mut absences = Vec::new();
+    for c in r_rangée.captures_iter(page) {
+        let état = attribut(&c[1], "data-status").unwrap_or_default().to_lowercase();
+        let retard = match (l.absence.iter().any(|a| état.contains(a)), l.retard.iter().any(|r| état.contains(r))) {
+            (true, _) => false,
+            (false, true) => true,
+            (false, false) => continue
+        };
+
+        let date = attribut(&c[1], "data-date").unwrap_or_default();
+        absences.push(Absence {
+            id_élève: lire_nombre(url, "l'identifiant de l'élève", &attribut(&c[1], "data-id").unwrap_or_default())?,
+            date: lire_date(&date, &l.mois).ok_or_else(|| {
+                Erreur::Page { url: url.to_string(), message: format!("la date d'absence {} est invalide", date) }
+            })?,
+            période: match attribut(&c[1], "data-period") {
+                Some(période) => lire_nombre(url, "la période", &période)?,
+                None => 1
             },
-            Result::Ok(res) => Err(Erreur::ÉtatInconnu(res.status())),
-            Result::Err(err) => Err(Erreur::Requête(err))
-        }
-    }
-
-    pub fn obtenir_données_élève(&self, élève: &Élève) -> Result<(Option<NaiveDate>, Vec<Contact>)> {
-        let res = self.client
-            .get(format!("https://french.compassforsuccess.ca/portal/gb/student/{}/gbInfo.do", élève.id))
-            .send();
-
-        // Incertain de "sept"
-        let mois = vec!["janv", "févr", "mars", "avr", "mai", "juin", "juil", "août", "sept", "oct", "nov", "déc"];
-        let r_date = Regex::new(r#"(?s:<th>Date de naissance</th>\s+<td>.+?>([0-9]+) ([^<>]+)\. ([0-9]+)</span>)"#).unwrap();
-        let r_majeur = Regex::new(r#"<STRONG>([^<>]+)</STRONG>"#).unwrap();
-        let r_contact = Regex::new(r#"(?s:<th>Nom.+?>([^<>]+)</span.+?([^<>]*)</span.+?Domicile.+?<td>([^<>]*).+?Travail.+?<td>([^<>]*).+?Cellulaire.+?<td>([^<>]*).+?Courriel.+?>([^<>]*)</a>.+?Correspondance.+?(green|red).+?Priorité de fermeture.+?>([0-9]+)</td>)"#).unwrap();
-        match res {
-            Result::Ok(res) if res.status() == StatusCode::OK => {
-                let page = res.text().unwrap_or(String::new());
-
-                let naissance = r_date
-                    .captures(&page)
-                    .map(|c|
-                        NaiveDate::from_ymd(
-                            c[3].parse().unwrap(),
-                            mois.iter().position(|&m| m == &c[2]).unwrap() as u32 + 1,
-                            c[1].parse().unwrap()
-                        ));
-
-                let mut contacts: Vec<_> = r_contact.captures_iter(&page).map(|c| {
-                    Contact {
-                        nom_complet: decode_html_entities(&c[1]).into(),
-                        relation: if !c[2].is_empty() && &c[2] != "Unknown" { Some(decode_html_entities(&c[2]).into()) } else { None },
-                        tel_domicile: if !c[3].is_empty() { Some(decode_html_entities(&c[3]).into()) } else { None },
-                        tel_travail: if !c[4].is_empty() { Some(decode_html_entities(&c[4]).into()) } else { None },
-                        tel_cellulaire: if !c[5].is_empty() { Some(decode_html_entities(&c[5]).into()) } else { None },
-                        courriel: if !c[6].is_empty() { Some(decode_html_entities(&c[6]).into()) } else { None },
-                        correspondance: &c[7] == "green",
-                        ordre: Some(c[8].parse().unwrap())
-                    }
-                }).collect();
-
-                if page.contains("Student is 18") {
-                    let contacts_majeur: Vec<_> = r_majeur.captures_iter(&page).filter_map(|c| {
-                        if &c[1] == "NONE" {
-                            None
-                        } else {
-                            Some(decode_html_entities(&c[1]).to_string())
+            retard
+        });
+    }
+
+    debug!("{} absences={} retards={}", url, absences.iter().filter(|a| !a.retard).count(), absences.iter().filter(|a| a.retard).count());
+    Ok(absences)
+}
+
+// Les attributs sont comparés par leur nom complet: « id » ne correspond pas à data-id.
+fn attribut(balise: &str, nom: &str) -> Option<String> {
+    static R_ATTRIBUT: OnceLock<Regex> = OnceLock::new();
+    let r_attribut = R_ATTRIBUT.get_or_init(|| Regex::new(r#"([A-Za-z_:][-A-Za-z0-9_:.]*)="([^"]*)""#).unwrap());
+    r_attribut.captures_iter(balise).find(|c| &c[1] == nom).map(|c| decode_html_entities(&c[2]).trim().to_string())
+}
+
+fn texte_cellule(html: &str) -> String {
+    static R_BALISE: OnceLock<Regex> = OnceLock::new();
+    let r_balise = R_BALISE.get_or_init(|| Regex::new(r"<[^>]*>").unwrap());
+    decode_html_entities(&r_balise.replace_all(html, "")).trim().to_string()
+}
+
+// Lit une date dont le mois est écrit en lettres, dans l'ordre français (12 sept. 2008) ou anglais (Sep 12, 2008), ou une
+// date au format ISO (2008-09-12).
+fn lire_date(texte: &str, mois: &[&str; 12]) -> Option<NaiveDate> {
+    let texte = decode_html_entities(texte).to_lowercase();
+    let parties: Vec<&str> = texte
+        .split(|c: char| c.is_whitespace() || ".,-/".contains(c))
+        .filter(|p| !p.is_empty())
+        .collect();
+    let nombres: Vec<&str> = parties.iter().copied().filter(|p| p.chars().all(|c| c.is_ascii_digit())).collect();
+    let mots: Vec<&str> = parties.iter().copied().filter(|p| !p.chars().all(|c| c.is_ascii_digit())).collect();
+
+    match (&nombres[..], &mots[..]) {
+        ([année, m, jour], []) if année.len() == 4 => {
+            NaiveDate::from_ymd_opt(année.parse().ok()?, m.parse().ok()?, jour.parse().ok()?)
+        },
+        ([a, b], [nom_mois]) => {
+            let m = mois.iter().position(|m| nom_mois.starts_with(m))? as u32 + 1;
+            let (année, jour) = match (a.len(), b.len()) {
+                (4, 1..=2) => (a, b),
+                (1..=2, 4) => (b, a),
+                _ => return None
+            };
+            NaiveDate::from_ymd_opt(année.parse().ok()?, m, jour.parse().ok()?)
+        },
+        _ => None
+    }
+}
+
+fn lire_nombre<N: std::str::FromStr>(url: &str, description: &str, texte: &str) -> Result<N> {
+    texte
+        .parse()
+        .map_err(|_| Erreur::Page { url: url.to_string(), message: format!("{} ({}) n'est pas un nombre valide", description, texte) })
+}
+
+// Un substitut local du portail pour les essais. Chaque requête est consignée (méthode, chemin et corps) et reçoit la
+// réponse de la première route dont le chemin se termine par le motif, ou 404.
+#[cfg(test)]
+pub mod substitut {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use super::{Langue, Portail};
+
+    pub struct Réponse {
+        état: u16,
+        location: Option<String>,
+        corps: String
+    }
+
+    impl Réponse {
+        pub fn page<S: Into<String>>(corps: S) -> Self {
+            Réponse { état: 200, location: None, corps: corps.into() }
+        }
+
+        pub fn redirection<S: Into<String>>(location: S) -> Self {
+            Réponse { état: 302, location: Some(location.into()), corps: String::new() }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Requête {
+        pub méthode: String,
+        pub chemin: String,
+        pub corps: String
+    }
+
+    pub struct Substitut {
+        pub portail: Portail,
+        requêtes: Arc<Mutex<Vec<Requête>>>
+    }
+
+    impl Substitut {
+        pub fn démarrer(routes: Vec<(&'static str, Réponse)>) -> Self {
+            let écouteur = TcpListener::bind("127.0.0.1:0").unwrap();
+            let portail = Portail { hôte: format!("http://{}", écouteur.local_addr().unwrap()), langue: Langue::Français };
+            let requêtes = Arc::new(Mutex::new(Vec::new()));
+
+            let journal = Arc::clone(&requêtes);
+            thread::spawn(move || {
+                for flux in écouteur.incoming() {
+                    let mut flux = match flux {
+                        Ok(flux) => flux,
+                        Err(_) => break
+                    };
+                    let mut lecteur = BufReader::new(flux.try_clone().unwrap());
+
+                    let mut ligne = String::new();
+                    lecteur.read_line(&mut ligne).unwrap();
+                    let mut parties = ligne.split_whitespace();
+                    let méthode = parties.next().unwrap_or_default().to_string();
+                    let chemin = parties.next().unwrap_or_default().to_string();
+
+                    let mut longueur = 0;
+                    loop {
+                        let mut entête = String::new();
+                        lecteur.read_line(&mut entête).unwrap();
+                        match entête.split_once(':') {
+                            Some((nom, valeur)) if nom.eq_ignore_ascii_case("content-length") => longueur = valeur.trim().parse().unwrap(),
+                            Some(_) => (),
+                            None => break
                         }
-                    }).collect();
-                    contacts = contacts.iter().cloned().filter(|c| contacts_majeur.contains(&c.nom_complet)).collect();
+                    }
+                    let mut corps = vec![0; longueur];
+                    lecteur.read_exact(&mut corps).unwrap();
+
+                    let réponse = routes.iter().find(|(motif, _)| chemin.split('?').next().unwrap().ends_with(motif)).map(|(_, r)| r);
+                    journal.lock().unwrap().push(Requête { méthode, chemin, corps: String::from_utf8(corps).unwrap() });
+
+                    let (état, location, corps) = match réponse {
+                        Some(r) => (r.état, r.location.as_deref(), r.corps.as_str()),
+                        None => (404, None, "")
+                    };
+                    let location = location.map(|l| format!("Location: {}\r\n", l)).unwrap_or_default();
+                    write!(
+                        flux,
+                        "HTTP/1.1 {} Substitut\r\n{}Content-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        état, location, corps.len(), corps
+                    ).unwrap();
                 }
+            });
+
+            Substitut { portail, requêtes }
+        }
+
+        pub fn requêtes(&self) -> Vec<Requête> {
+            self.requêtes.lock().unwrap().clone()
+        }
+
+        pub fn envois(&self, motif: &str) -> Vec<String> {
+            self.requêtes().into_iter().filter(|r| r.méthode == "POST" && r.chemin.ends_with(motif)).map(|r| r.corps).collect()
+        }
+    }
+}
 
-                Ok((naissance, contacts))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::substitut::{Réponse, Substitut};
+
+    fn substitut_carnet(enregistrement: Réponse) -> Substitut {
+        Substitut::démarrer(vec![
+            ("/auth/login.do", Réponse::redirection("/portal/home.do")),
+            ("/gb/class/42/gradebook.do", Réponse::page(include_str!("../fixtures/encompass/gradebook.html"))),
+            ("/gb/class/42/saveMarks.do", enregistrement)
+        ])
+    }
+
+    #[test]
+    fn connexion() {
+        let substitut = substitut_carnet(Réponse::page(""));
+        ClientEncompass::new(&substitut.portail, "lea.tremblay", "secret & 1").unwrap();
+        assert_eq!(substitut.envois("/auth/login.do"), vec!["username=lea.tremblay&password=secret+%26+1"]);
+
+        let refus = Substitut::démarrer(vec![("/auth/login.do", Réponse::page("<form>"))]);
+        assert!(matches!(ClientEncompass::new(&refus.portail, "u", "p"), Err(Erreur::IdentifiantsInvalides)));
+    }
+
+    #[test]
+    fn envoi_notes() {
+        let substitut = substitut_carnet(Réponse::redirection("/portal/gb/class/42/gradebook.do"));
+        let client = ClientEncompass::new(&substitut.portail, "u", "p").unwrap();
+
+        assert_eq!(client.obtenir_carnet(42).unwrap().travaux.len(), 3);
+        client.envoyer_notes(42, &[(101, 5001, 18.0), (102, 5002, 32.25)]).unwrap();
+        assert_eq!(substitut.envois("/saveMarks.do"), vec!["mark_101_5001=18&mark_102_5002=32.25"]);
+    }
+
+    #[test]
+    fn envoi_notes_session_expirée() {
+        let substitut = substitut_carnet(Réponse::redirection("/portal/auth/login.do?expired=true"));
+        let client = ClientEncompass::new(&substitut.portail, "u", "p").unwrap();
+
+        match client.envoyer_notes(42, &[(101, 5001, 18.0)]) {
+            Err(Erreur::Page { url, message }) => {
+                assert!(url.ends_with("/portal/gb/class/42/saveMarks.do"));
+                assert!(message.contains("session a expiré"));
             },
-            Result::Ok(res) => Err(Erreur::ÉtatInconnu(res.status())),
-            Result::Err(err) => Err(Erreur::Requête(err))
+            _ => panic!("la session expirée n'est pas signalée")
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn carnet() {
+        let carnet = lire_carnet("gradebook.do", include_str!("../fixtures/encompass/gradebook.html")).unwrap();
+
+        let catégories: Vec<(&str, f64)> = carnet.catégories.iter().map(|c| (c.nom.as_str(), c.pondération)).collect();
+        assert_eq!(catégories, vec![("Connaissance et compréhension", 25.0), ("Mise en application", 12.5), ("Examen", 1.0)]);
+
+        let travaux: Vec<(i32, &str, &str, f64, f64)> = carnet.travaux
+            .iter()
+            .map(|t| (t.id, t.nom.as_str(), t.catégorie.as_str(), t.total, t.pondération))
+            .collect();
+        assert_eq!(travaux, vec![
+            (101, "Test 1", "Connaissance et compréhension", 20.0, 1.0),
+            (102, "Projet & présentation", "Mise en application", 37.5, 2.0),
+            (103, "Examen", "Examen", 10.0, 0.5)
+        ]);
+
+        let notes: Vec<(i32, i32, Option<f64>)> = carnet.notes.iter().map(|n| (n.id_élève, n.id_travail, n.note)).collect();
+        assert_eq!(notes, vec![
+            (5001, 101, Some(75.0)),
+            (5001, 102, None),
+            (5001, 103, Some(75.0)),
+            (5002, 101, Some(87.5)),
+            (5002, 102, Some(80.0)),
+            (5002, 103, None)
+        ]);
+    }
+
+    #[test]
+    fn suivi() {
+        let cours = lire_tableau_suivi("suivi.do", include_str!("../fixtures/encompass/suivi.html"), 4321, &LIBELLÉS_FRANÇAIS).unwrap();
+        assert_eq!(cours.len(), 1);
+        assert_eq!((cours[0].id_groupe, cours[0].code.as_str()), (4321, "MPM2D-01"));
+
+        let élèves: Vec<(i32, &str, &Suivi)> = cours[0].élèves.iter().map(|é| (é.id, é.prénom.as_str(), &é.suivi)).collect();
+        let suivis: Vec<_> = élèves.iter().map(|(id, prénom, s)| (*id, *prénom, s.niveau, s.crédits, s.pei, s.alf)).collect();
+        assert_eq!(suivis, vec![
+            (5001, "Amélie", Some(10), Some(14.5), Some(true), Some(false)),
+            (5002, "Jean", Some(11), Some(22.0), Some(false), Some(true))
+        ]);
+
+        let titres: Vec<&str> = cours[0].élèves[0].suivi.indicateurs.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(titres, vec!["Niveau", "Crédits obtenus", "PEI", "ALF", "Risque", "Risque"]);
+    }
+
+    #[test]
+    fn suivi_entête_incomplète() {
+        let page = r#"<table><tr><th>Niveau</th><th>Crédits</th></tr>
+            <tr data-id="7"><td><a>Léa</a></td><td>Roy</td><td>10</td><td>4</td><td></td><td></td><td></td><td>ENG1D</td></tr></table>"#;
+        let cours = lire_tableau_suivi("suivi.do", page, 1, &LIBELLÉS_FRANÇAIS).unwrap();
+
+        let suivi = &cours[0].élèves[0].suivi;
+        assert_eq!((suivi.niveau, suivi.crédits), (None, None));
+        let titres: Vec<&str> = suivi.indicateurs.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(titres, vec!["Colonne 3", "Colonne 4", "Colonne 5", "Colonne 6"]);
+    }
+
+    type Champs<'a> = (Option<&'a str>, Option<&'a str>, Option<&'a str>, Option<u32>, Option<&'a str>);
+
+    fn fiche(données: &DonnéesÉlève) -> Champs<'_> {
+        let f = &données.fiche;
+        (f.courriel.as_deref(), f.niso.as_deref(), f.foyer.as_deref(), f.niveau, f.conseiller.as_deref())
+    }
+
+    #[test]
+    fn données_élève() {
+        let élève = Élève::new(5001, "Amélie", "Tremblay");
+        let données = lire_données_élève("gbInfo.do", include_str!("../fixtures/encompass/fiche.html"), &élève, &LIBELLÉS_FRANÇAIS).unwrap();
+
+        assert_eq!(données.naissance, NaiveDate::from_ymd_opt(2012, 9, 12));
+        assert!(!données.majeur);
+        assert_eq!(fiche(&données), (Some("amelie.tremblay@ecole.ca"), Some("123456789"), Some("B-204"), Some(10), Some("Mme Gagnon")));
+
+        let contacts: Vec<(&str, Option<&str>, bool, Option<u32>)> = données.contacts
+            .iter()
+            .map(|c| (c.nom_complet.as_str(), c.relation.as_deref(), c.correspondance, c.ordre))
+            .collect();
+        assert_eq!(contacts, vec![("Marie Tremblay", Some("Mère"), true, Some(1)), ("Luc Tremblay", None, false, Some(2))]);
+        let coordonnées: Vec<[Option<&str>; 4]> = données.contacts
+            .iter()
+            .map(|c| [c.tel_domicile.as_deref(), c.tel_travail.as_deref(), c.tel_cellulaire.as_deref(), c.courriel.as_deref()])
+            .collect();
+        assert_eq!(coordonnées, vec![
+            [Some("613-555-1234"), None, Some("613-555-9876"), Some("marie@exemple.ca")],
+            [None, Some("613-555-4321"), None, None]
+        ]);
+    }
+
+    #[test]
+    fn consentements_majeur() {
+        let élève = Élève::new(5001, "Amélie", "Tremblay");
+        let fiche = include_str!("../fixtures/encompass/fiche.html").replace("12 sept. 2012", "12 sept. 2000");
+        let consentements = |données: &DonnéesÉlève| -> Vec<(String, bool)> {
+            données.contacts.iter().map(|c| (c.nom_complet.clone(), c.consentement)).collect()
+        };
+
+        // Sans le bloc d'EnCompass, la date de naissance ne suffit pas à lire des consentements.
+        let données = lire_données_élève("gbInfo.do", &fiche, &élève, &LIBELLÉS_FRANÇAIS).unwrap();
+        assert!(!données.majeur);
+        assert_eq!(consentements(&données), vec![("Marie Tremblay".into(), false), ("Luc Tremblay".into(), false)]);
+
+        let page = fiche.replace("<div class=\"contacts\">", "
+            <p><STRONG>Marie Tremblay</STRONG> est la personne à joindre en premier.</p>
+            <div class=\"alert\">Student is 18 and consents to contact with: <STRONG>Luc Tremblay</STRONG></div>
+            <div class=\"contacts\">");
+        let données = lire_données_élève("gbInfo.do", &page, &élève, &LIBELLÉS_FRANÇAIS).unwrap();
+        assert!(données.majeur);
+        assert_eq!(consentements(&données), vec![("Marie Tremblay".into(), false), ("Luc Tremblay".into(), true)]);
+
+        let page = fiche.replace("<div class=\"contacts\">", "<div>Student is 18 and consents to contact with: <STRONG>NONE</STRONG></div><div class=\"contacts\">");
+        let données = lire_données_élève("gbInfo.do", &page, &élève, &LIBELLÉS_FRANÇAIS).unwrap();
+        assert!(données.majeur);
+        assert_eq!(consentements(&données), vec![("Marie Tremblay".into(), false), ("Luc Tremblay".into(), false)]);
+    }
+
+    #[test]
+    fn champs_fiche() {
+        let élève = Élève::new(7, "Léa", "Roy");
+        let lire = |page: &str, l| lire_données_élève("gbInfo.do", page, &élève, l).unwrap();
+
+        let page = "<tr><th>Courriel scolaire</th><td>lea@ecole.ca</td></tr>
+            <tr><th>Numéro d'immatriculation scolaire de l'Ontario</th><td>987654321</td></tr>
+            <tr><th> Foyer : </th><td>112</td></tr><tr><th>Niveau</th><td>12</td></tr><tr><th>Orientation</th><td>M. Roy</td></tr>";
+        assert_eq!(fiche(&lire(page, &LIBELLÉS_FRANÇAIS)), (Some("lea@ecole.ca"), Some("987654321"), Some("112"), Some(12), Some("M. Roy")));
+
+        let page = "<tr><th>Student Email</th><td>lea@school.ca</td></tr><tr><th>OEN</th><td>987 654 321</td></tr>
+            <tr><th>Homeroom</th><td>112</td></tr><tr><th>Grade</th><td>Grade 9</td></tr><tr><th>Guidance Counsellor</th><td>Mr. Roy</td></tr>";
+        assert_eq!(fiche(&lire(page, &LIBELLÉS_ANGLAIS)), (Some("lea@school.ca"), Some("987654321"), Some("112"), Some(9), Some("Mr. Roy")));
+
+        // Un NISO incomplet est ignoré, tout comme un champ vide ou un libellé qui n'est qu'un préfixe.
+        let page = "<tr><th>NISO</th><td>12345</td></tr><tr><th>Salle-foyer</th><td> </td></tr><tr><th>Niveau de risque</th><td>3</td></tr>";
+        assert_eq!(fiche(&lire(page, &LIBELLÉS_FRANÇAIS)), (None, None, None, None, None));
+    }
+
+    #[test]
+    fn carnet_travail_inconnu() {
+        let page = r#"<table><tr><th data-assignment-id="1" data-out-of="10">A</th></tr>
+            <tr data-id="7"><td data-assignment-id="2">5</td></tr></table>"#;
+        assert!(matches!(lire_carnet("gradebook.do", page), Err(Erreur::Page { .. })));
+    }
+
+    fn absences(page: &str, langue: Langue) -> Vec<(i32, String, u32, bool)> {
+        lire_absences("attendance.do", page, langue.libellés())
+            .unwrap()
+            .iter()
+            .map(|a| (a.id_élève, a.date.to_string(), a.période, a.retard))
+            .collect()
+    }
+
+    #[test]
+    fn absences_anglais() {
+        assert_eq!(absences(include_str!("../fixtures/encompass/attendance_en.html"), Langue::Anglais), vec![
+            (5001, "2030-09-12".into(), 1, false),
+            (5001, "2030-09-12".into(), 2, false),
+            (5002, "2030-09-13".into(), 1, true),
+            (5002, "2030-09-14".into(), 1, true)
+        ]);
+    }
+
+    #[test]
+    fn absences_français() {
+        assert_eq!(absences(include_str!("../fixtures/encompass/attendance_fr.html"), Langue::Français), vec![
+            (5001, "2030-09-12".into(), 1, false),
+            (5002, "2031-02-01".into(), 3, true)
+        ]);
+    }
+
+    #[test]
+    fn absences_date_invalide() {
+        let page = r#"<tr data-id="5001" data-date="Smarch 12, 2030" data-status="Absent">"#;
+        assert!(matches!(lire_absences("attendance.do", page, Langue::Anglais.libellés()), Err(Erreur::Page { .. })));
+    }
+
+    #[test]
+    fn dates() {
+        let lire = |texte, langue: Langue| lire_date(texte, &langue.libellés().mois);
+        assert_eq!(lire("12 sept. 2008", Langue::Français), NaiveDate::from_ymd_opt(2008, 9, 12));
+        assert_eq!(lire("Sep 12, 2008", Langue::Anglais), NaiveDate::from_ymd_opt(2008, 9, 12));
+        assert_eq!(lire("2008-09-12", Langue::Anglais), NaiveDate::from_ymd_opt(2008, 9, 12));
+        assert_eq!(lire("12 sept. 08", Langue::Français), None);
+        assert_eq!(lire("Sep 12, 208", Langue::Anglais), None);
+        assert_eq!(lire("12 sept. 20080", Langue::Français), None);
+        assert_eq!(lire("12-09-08", Langue::Français), None);
+        assert_eq!(lire("31 févr. 2008", Langue::Français), None);
+    }
+
+    #[test]
+    fn attributs() {
+        let balise = r#"<td class="mark" data-id="7" id="cellule" title="L&#39;&eacute;l&egrave;ve ">"#;
+        assert_eq!(attribut(balise, "id").as_deref(), Some("cellule"));
+        assert_eq!(attribut(balise, "data-id").as_deref(), Some("7"));
+        assert_eq!(attribut(balise, "title").as_deref(), Some("L'élève"));
+        assert_eq!(attribut(balise, "alt"), None);
+    }
+
+    #[test]
+    fn hôtes_locaux() {
+        assert!(est_local("127.0.0.1:8080"));
+        assert!(est_local("localhost"));
+        assert!(est_local("[::1]:8080"));
+        assert!(!est_local("portail.exemple.ca"));
+        assert!(!est_local("127.0.0.1.exemple.ca"));
+    }
+}
@@ -1,22 +1,126 @@
+use std::process::ExitCode;
+
 #[derive(Debug)]
 pub enum Erreur {
     Abandonné,
-    Arbre(String),
-    ÉtatInconnu(reqwest::StatusCode),
+    Arguments(String),
+    Classeur { fichier: String, feuille: String, ligne: Option<u32>, message: String },
+    Contexte(String, Box<Erreur>),
+    Csv(csv::Error),
+    ÉtatInconnu { url: String, état: reqwest::StatusCode },
     IdentifiantsInvalides,
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    NoeudCyclique(usize),
+    NoeudInvalide(usize),
     Ods(spreadsheet_ods::OdsError),
+    Page { url: String, message: String },
     Requête(reqwest::Error),
-    Sqlite(rusqlite::Error)
+    Session(String),
+    Sqlite(rusqlite::Error),
+    Structure(String),
+    Toml(String)
 }
 
 pub type Result<T, E = Erreur> = std::result::Result<T, E>;
 
+impl Erreur {
+    // 2: arguments invalides, 3: travail abandonné, 4: identifiants refusés, 5: échec d'une requête ou page
+    // inattendue, 6: fichier illisible ou invalide, 7: erreur de la base de données ou erreur interne.
+    pub fn code_sortie(&self) -> u8 {
+        match self {
+            Erreur::Contexte(_, e) => e.code_sortie(),
+            Erreur::Arguments(_) | Erreur::Session(_) => 2,
+            Erreur::Abandonné => 3,
+            Erreur::IdentifiantsInvalides => 4,
+            Erreur::ÉtatInconnu { .. } | Erreur::Page { .. } | Erreur::Requête(_) => 5,
+            Erreur::Classeur { .. } | Erreur::Csv(_) | Erreur::Io(_) | Erreur::Json(_) | Erreur::Ods(_)
+                | Erreur::Structure(_) | Erreur::Toml(_) => 6,
+            Erreur::NoeudCyclique(_) | Erreur::NoeudInvalide(_) | Erreur::Sqlite(_) => 7
+        }
+    }
+
+    // Le message de l'erreur suivi de celui de chacune de ses causes (p. ex. « Fichier x.toml: ... »). Les messages des
+    // erreurs des bibliothèques sont déjà compris dans celui de l'erreur qui les enveloppe.
+    pub fn message_complet(&self) -> String {
+        let mut message = self.to_string();
+        let mut cause = std::error::Error::source(self);
+        while let Some(e) = cause {
+            let texte = e.to_string();
+            if !message.contains(&texte) {
+                message = format!("{}: {}", message, texte);
+            }
+            cause = e.downcast_ref::<Erreur>().and_then(std::error::Error::source);
+        }
+
+        message
+    }
+}
+
+// Ajoute à une erreur ce qui était en traitement (p. ex. l'élève ou le fichier), sans perdre l'erreur d'origine.
+pub trait Contexte<T> {
+    fn contexte<C: Into<String>, F: FnOnce() -> C>(self, contexte: F) -> Result<T>;
+}
+
+impl<T, E: Into<Erreur>> Contexte<T> for std::result::Result<T, E> {
+    fn contexte<C: Into<String>, F: FnOnce() -> C>(self, contexte: F) -> Result<T> {
+        self.map_err(|e| Erreur::Contexte(contexte().into(), Box::new(e.into())))
+    }
+}
+
+// Termine le programme avec le code de sortie de l'erreur, après l'avoir journalisée. L'erreur est affichée directement
+// si la journalisation n'a pas pu être initialisée.
+pub fn terminer(résultat: Result<()>) -> ExitCode {
+    match résultat {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if log::log_enabled!(log::Level::Error) {
+                log::error!("{}", e.message_complet());
+            } else {
+                eprintln!("Erreur: {}", e.message_complet());
+            }
+            log::logger().flush();
+            ExitCode::from(e.code_sortie())
+        }
+    }
+}
+
 impl From<rusqlite::Error> for Erreur {
     fn from(err: rusqlite::Error) -> Erreur {
         Erreur::Sqlite(err)
     }
 }
 
+impl From<csv::Error> for Erreur {
+    fn from(err: csv::Error) -> Erreur {
+        Erreur::Csv(err)
+    }
+}
+
+impl From<std::io::Error> for Erreur {
+    fn from(err: std::io::Error) -> Erreur {
+        Erreur::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Erreur {
+    fn from(err: serde_json::Error) -> Erreur {
+        Erreur::Json(err)
+    }
+}
+
+impl From<toml::de::Error> for Erreur {
+    fn from(err: toml::de::Error) -> Erreur {
+        Erreur::Toml(err.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for Erreur {
+    fn from(err: toml::ser::Error) -> Erreur {
+        Erreur::Toml(err.to_string())
+    }
+}
+
 impl From<reqwest::Error> for Erreur {
     fn from(err: reqwest::Error) -> Erreur {
         Erreur::Requête(err)
@@ -33,12 +137,60 @@ impl std::fmt::Display for Erreur {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match &self {
             Erreur::Abandonné => f.write_str("Le travail a été abandonné."),
-            Erreur::Arbre(e) => f.write_str(e),
-            Erreur::ÉtatInconnu(état) => f.write_str(&format!("Le code d'état ({}) de la requête envoyé est inattendu.", état)),
+            Erreur::Arguments(e) => f.write_str(e),
+            Erreur::Classeur { fichier, feuille, ligne: Some(ligne), message } =>
+                write!(f, "{} (fichier {}, feuille {}, ligne {})", message, fichier, feuille, ligne),
+            Erreur::Classeur { fichier, feuille, ligne: None, message } =>
+                write!(f, "{} (fichier {}, feuille {})", message, fichier, feuille),
+            Erreur::Contexte(contexte, _) => f.write_str(contexte),
+            Erreur::Csv(e) => write!(f, "Une erreur s'est produite lors de l'écriture d'un fichier CSV: {}.", e),
+            Erreur::ÉtatInconnu { url, état } => write!(f, "Le code d'état ({}) de la requête envoyée à {} est inattendu.", état, url),
             Erreur::IdentifiantsInvalides => f.write_str("Le nom d'utilisateur et le mot de passe sont invalides."),
-            Erreur::Ods(e) => f.write_str(&format!("Une erreur s'est produite lors du traitement d'un fichier ODS: {}.", e)),
-            Erreur::Requête(e) => f.write_str(&format!("Une erreur s'est produite lors de l'envoie de la requête: {}.", e)),
-            Erreur::Sqlite(e) => f.write_str(&format!("Une erreur SQLite s'est produite: {}.", e))
+            Erreur::Io(e) => write!(f, "Une erreur s'est produite lors de l'accès à un fichier: {}.", e),
+            Erreur::Json(e) => write!(f, "Une erreur s'est produite lors du traitement d'un fichier JSON: {}.", e),
+            Erreur::NoeudCyclique(idx) => write!(f, "Le noeud {} ne peut pas être placé sous lui-même.", idx),
+            Erreur::NoeudInvalide(idx) => write!(f, "Le noeud {} est invalide.", idx),
+            Erreur::Ods(e) => write!(f, "Une erreur s'est produite lors du traitement d'un fichier ODS: {}.", e),
+            Erreur::Page { url, message } => write!(f, "La page {} est inattendue: {}.", url, message),
+            Erreur::Requête(e) => write!(f, "Une erreur s'est produite lors de l'envoi de la requête: {}.", e),
+            Erreur::Session(e) => f.write_str(e),
+            Erreur::Sqlite(e) => write!(f, "Une erreur SQLite s'est produite: {}.", e),
+            Erreur::Structure(e) => f.write_str(e),
+            Erreur::Toml(e) => write!(f, "Une erreur s'est produite lors du traitement d'un fichier TOML: {}.", e)
         }
     }
-}
\ No newline at end of file
+}
+
+impl std::error::Error for Erreur {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Erreur::Contexte(_, e) => Some(e.as_ref()),
+            Erreur::Csv(e) => Some(e),
+            Erreur::Io(e) => Some(e),
+            Erreur::Json(e) => Some(e),
+            Erreur::Ods(e) => Some(e),
+            Erreur::Requête(e) => Some(e),
+            Erreur::Sqlite(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chaîne() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "introuvable");
+        let e = Err::<(), _>(io).contexte(|| "Fichier structure.toml").contexte(|| "Cours MHF4U").unwrap_err();
+
+        assert_eq!(e.to_string(), "Cours MHF4U");
+        assert_eq!(e.code_sortie(), 6);
+        assert_eq!(
+            e.message_complet(),
+            "Cours MHF4U: Fichier structure.toml: Une erreur s'est produite lors de l'accès à un fichier: introuvable."
+        );
+        assert_eq!(Erreur::Arguments("L'argument -x est inconnu.".into()).message_complet(), "L'argument -x est inconnu.");
+    }
+}
@@ -0,0 +1,166 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::erreur::{Erreur, Result};
+
+// Les étiquettes sont séparées par des virgules (p. ex. « PEI, Virtuel »).
+pub fn lire_étiquettes(texte: &str) -> Vec<String> {
+    let mut étiquettes: Vec<String> = Vec::new();
+    for étiquette in texte.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        if !étiquettes.iter().any(|e| e == étiquette) {
+            étiquettes.push(étiquette.to_string());
+        }
+    }
+
+    étiquettes
+}
+
+// Les exportations ne retiennent que les élèves de la table temporaire élève_filtré, remplie selon le filtre: ceux qui
+// portent au moins une des étiquettes, ou tous les élèves si le filtre est vide.
+pub fn filtrer_élèves(conn: &Connection, étiquettes: &[String]) -> Result<()> {
+    conn.execute_batch("
+        CREATE TEMP TABLE IF NOT EXISTS élève_filtré (id_élève INTEGER PRIMARY KEY);
+        DELETE FROM élève_filtré;
+    ")?;
+    conn.execute("
+        INSERT INTO élève_filtré(id_élève)
+            SELECT id FROM élève
+                WHERE ?1 = '[]' OR id IN (SELECT id_élève FROM élève_étiquette_nom WHERE nom IN (SELECT value FROM json_each(?1)));
+    ", [serde_json::to_string(étiquettes)?])?;
+
+    Ok(())
+}
+
+// Les étiquettes déduites du tableau de suivi d'EnCompass (p. ex. « EnCompass:PEI ») ne sont pas enregistrées; elles
+// suivent les indicateurs de l'élève à chaque importation et ne peuvent être ni créées, ni attribuées, ni retirées.
+const PRÉFIXE_ENCOMPASS: &str = "EnCompass:";
+
+fn vérifier_nom(nom: &str) -> Result<()> {
+    if nom.starts_with(PRÉFIXE_ENCOMPASS) {
+        return Err(Erreur::Arguments(format!("L'étiquette {} est déduite d'EnCompass et ne peut être modifiée.", nom)));
+    }
+
+    Ok(())
+}
+
+pub fn obtenir_étiquette(conn: &Connection, nom: &str) -> Result<i64> {
+    vérifier_nom(nom)?;
+    conn.execute("INSERT OR IGNORE INTO étiquette(nom) VALUES (?1)", [nom])?;
+
+    Ok(conn.query_row("SELECT id FROM étiquette WHERE nom = ?1", [nom], |r| r.get(0))?)
+}
+
+fn trouver_étiquette(conn: &Connection, nom: &str) -> Result<i64> {
+    vérifier_nom(nom)?;
+    conn.query_row("SELECT id FROM étiquette WHERE nom = ?1", [nom], |r| r.get(0))
+        .optional()?
+        .ok_or_else(|| Erreur::Arguments(format!("L'étiquette {} n'existe pas.", nom)))
+}
+
+pub fn supprimer_étiquette(conn: &mut Connection, nom: &str) -> Result<()> {
+    let id_étiquette = trouver_étiquette(conn, nom)?;
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM élève_étiquette WHERE id_étiquette = ?1", [id_étiquette])?;
+    tx.execute("DELETE FROM étiquette WHERE id = ?1", [id_étiquette])?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+// Retourne le nom et le nombre d'élèves de chaque étiquette.
+pub fn lister_étiquettes(conn: &Connection) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare("
+        SELECT étiquette.nom, COUNT(élève_étiquette.id_élève)
+            FROM étiquette
+            LEFT JOIN élève_étiquette ON élève_étiquette.id_étiquette = étiquette.id
+            GROUP BY étiquette.id
+            ORDER BY étiquette.nom;
+    ")?;
+    let étiquettes = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(étiquettes)
+}
+
+// Retourne vrai si l'élève n'avait pas déjà l'étiquette.
+pub fn étiqueter(conn: &Connection, id_élève: i64, nom: &str) -> Result<bool> {
+    let id_étiquette = obtenir_étiquette(conn, nom)?;
+
+    Ok(conn.execute("
+        INSERT OR IGNORE INTO élève_étiquette(id_élève, id_étiquette) VALUES (?1, ?2);
+    ", params![id_élève, id_étiquette])? > 0)
+}
+
+// Retourne vrai si l'élève avait l'étiquette.
+pub fn retirer_étiquette(conn: &Connection, id_élève: i64, nom: &str) -> Result<bool> {
+    let id_étiquette = trouver_étiquette(conn, nom)?;
+
+    Ok(conn.execute("
+        DELETE FROM élève_étiquette WHERE id_élève = ?1 AND id_étiquette = ?2;
+    ", params![id_élève, id_étiquette])? > 0)
+}
+
+// Un élève est désigné par son prénom, ou son prénom préféré, suivi de son nom parmi les élèves inscrits à la session.
+pub fn trouver_élève(conn: &Connection, nom_complet: &str, id_session: i64) -> Result<i64> {
+    let mut stmt = conn.prepare("
+        SELECT DISTINCT élève.id
+            FROM élève
+            JOIN inscription ON inscription.id_élève = élève.id
+            JOIN cours ON cours.id = inscription.id_cours
+            WHERE cours.id_session = ?2
+                AND (élève.prénom || ' ' || élève.nom = ?1 OR élève.prénom_préféré || ' ' || élève.nom = ?1);
+    ")?;
+    let ids: Vec<i64> = stmt
+        .query_map(params![nom_complet.trim(), id_session], |r| r.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    match ids[..] {
+        [id_élève] => Ok(id_élève),
+        [] => Err(Erreur::Arguments(format!("L'élève {} est introuvable dans la session.", nom_complet))),
+        _ => Err(Erreur::Arguments(format!("Plusieurs élèves se nomment {}.", nom_complet)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bd;
+
+    fn filtrés(conn: &Connection, étiquettes: &[&str]) -> Vec<i64> {
+        filtrer_élèves(conn, &étiquettes.iter().map(|e| e.to_string()).collect::<Vec<_>>()).unwrap();
+        conn.prepare("SELECT id_élève FROM élève_filtré ORDER BY id_élève;").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn filtre() {
+        let conn = bd::ouvrir(None).unwrap();
+        conn.execute_batch("INSERT INTO élève(id, prénom, nom) VALUES (1, 'Léa', 'Tremblay'), (2, 'Noah', 'Gagnon'), (3, 'Emma', 'Roy');").unwrap();
+        étiqueter(&conn, 1, "Virtuel").unwrap();
+        étiqueter(&conn, 2, "Sport").unwrap();
+        étiqueter(&conn, 3, "Virtuel").unwrap();
+
+        assert_eq!(filtrés(&conn, &[]), vec![1, 2, 3]);
+        assert_eq!(filtrés(&conn, &["Virtuel"]), vec![1, 3]);
+        assert_eq!(filtrés(&conn, &["Sport", "Virtuel"]), vec![1, 2, 3]);
+        assert_eq!(filtrés(&conn, &["Inconnue"]), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn étiquettes_encompass() {
+        let conn = bd::ouvrir(None).unwrap();
+        conn.execute_batch("
+            INSERT INTO élève(id, prénom, nom, pei, alf, niveau) VALUES (1, 'Léa', 'Tremblay', 1, 0, 10), (2, 'Noah', 'Gagnon', 0, 0, NULL);
+        ").unwrap();
+        étiqueter(&conn, 2, "PEI").unwrap();
+
+        assert_eq!(filtrés(&conn, &["EnCompass:PEI"]), vec![1]);
+        assert_eq!(filtrés(&conn, &["EnCompass:Niveau 10"]), vec![1]);
+        assert_eq!(filtrés(&conn, &["PEI"]), vec![2]);
+
+        assert!(matches!(étiqueter(&conn, 2, "EnCompass:PEI"), Err(Erreur::Arguments(_))));
+        assert!(matches!(retirer_étiquette(&conn, 1, "EnCompass:PEI"), Err(Erreur::Arguments(_))));
+        assert!(matches!(obtenir_étiquette(&conn, "EnCompass:ALF"), Err(Erreur::Arguments(_))));
+    }
+}
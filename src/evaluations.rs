@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::path::Path;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use crate::erreur::{Contexte, Erreur, Result};
+use crate::foret::{Forêt, IdNoeud};
+use crate::sessions::trouver_cours;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoteManquante {
+    Ignorer,
+    Zéro,
+    Incomplet
+}
+
+// Un composant sans résultat est ignoré dans la moyenne, compte pour zéro ou rend ses ascendants incomplets.
+pub fn lire_politique(politique: &str) -> Result<NoteManquante> {
+    match politique.trim().to_lowercase().as_str() {
+        "ignorer" => Ok(NoteManquante::Ignorer),
+        "zéro" | "zero" => Ok(NoteManquante::Zéro),
+        "incomplet" => Ok(NoteManquante::Incomplet),
+        _ => Err(Erreur::Arguments(format!("La politique {} est invalide (ignorer, zéro ou incomplet).", politique)))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub id: i64,
+    pub nom: String,
+    pub pondération: f64,
+    pub échelle: Option<String>,
+    pub formule: Option<String>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Agrégat {
+    pub valeur: Option<f64>,
+    pub complet: bool
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Niveau {
+    pub nom: String,
+    pub min: f64,
+    pub max: f64
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Échelle {
+    pub nom: String,
+    #[serde(rename = "precision", alias = "précision")]
+    pub précision: i64,
+    pub min: f64,
+    pub max: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub niveaux: Vec<Niveau>
+}
+
+fn pondération_défaut() -> f64 {
+    1.
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ItemStructure {
+    pub nom: String,
+    #[serde(rename = "ponderation", alias = "pondération", default = "pondération_défaut")]
+    pub pondération: f64,
+    #[serde(rename = "echelle", alias = "échelle", default, skip_serializing_if = "Option::is_none")]
+    pub échelle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formule: Option<String>
+}
+
+// Structure des évaluations d'un cours, indépendante des identifiants de la base de données. Les clés sont sans accents
+// puisque TOML ne les permet pas sans guillemets.
+#[derive(Deserialize, Serialize)]
+pub struct Structure {
+    pub cours: String,
+    #[serde(rename = "echelles", alias = "échelles", default, skip_serializing_if = "Vec::is_empty")]
+    pub échelles: Vec<Échelle>,
+    #[serde(rename = "evaluations", alias = "évaluations")]
+    pub évaluations: Forêt<ItemStructure>
+}
+
+pub fn charger_évaluations(conn: &Connection, id_cours: i64) -> Result<Forêt<Item>> {
+    let mut évaluations = Forêt::new();
+    let mut ids = HashMap::new();
+
+    // Les ascendants sont toujours lus avant leurs descendants.
+    let mut stmt = conn.prepare("
+        WITH RECURSIVE
+            arbre(id, nom, id_parent, pondération, id_échelle, formule, niveau, indice) AS (
+                SELECT id, nom, id_parent, pondération, id_échelle, formule, 0, indice
+                    FROM évaluation_item
+                    WHERE id_cours = ?1 AND id_parent IS NULL
+                UNION ALL
+                SELECT éi.id, éi.nom, éi.id_parent, éi.pondération, éi.id_échelle, éi.formule, arbre.niveau + 1, éi.indice
+                    FROM évaluation_item AS éi
+                    JOIN arbre ON éi.id_parent = arbre.id
+            )
+        SELECT arbre.id, arbre.nom, arbre.id_parent, arbre.pondération, échelle.nom, arbre.formule
+            FROM arbre
+            LEFT JOIN échelle ON échelle.id = arbre.id_échelle
+            ORDER BY arbre.niveau, arbre.id_parent, arbre.indice;
+    ")?;
+    let mut rangées = stmt.query([id_cours])?;
+    while let Some(r) = rangées.next()? {
+        let item = Item {
+            id: r.get(0)?,
+            nom: r.get(1)?,
+            pondération: r.get(3)?,
+            échelle: r.get(4)?,
+            formule: r.get(5)?
+        };
+        let id = item.id;
+        let id_parent: Option<i64> = r.get(2)?;
+        let id_noeud = match id_parent {
+            Some(id_parent) => évaluations.ajouter_descendant(ids[&id_parent], item)?,
+            None => évaluations.créer(item)
+        };
+        ids.insert(id, id_noeud);
+    }
+
+    Ok(évaluations)
+}
+
+// Le chemin d'un item depuis son évaluation, p. ex. « Examen / Partie A / Question 1 ».
+pub fn chemin(évaluations: &Forêt<Item>, id: IdNoeud) -> Result<String> {
+    let mut noms = évaluations.ascendants(id)?.map(|a| Ok(évaluations.val(a)?.nom.as_str())).collect::<Result<Vec<_>>>()?;
+    noms.reverse();
+    noms.push(&évaluations.val(id)?.nom);
+
+    Ok(noms.join(" / "))
+}
+
+fn moyenne_pondérée(descendants: &[(f64, Agrégat)], politique: NoteManquante) -> Agrégat {
+    let complet = descendants.iter().all(|(_, a)| a.complet);
+    if politique == NoteManquante::Incomplet && !complet {
+        return Agrégat { valeur: None, complet };
+    }
+
+    let (somme, poids) = descendants
+        .iter()
+        .filter_map(|(p, a)| a.valeur.map(|v| (p * v, *p)))
+        .fold((0., 0.), |(s, p), (sv, pv)| (s + sv, p + pv));
+
+    Agrégat {
+        valeur: if poids > 0. { Some(somme / poids) } else { None },
+        complet
+    }
+}
+
+// Les résultats des sections et des évaluations sont les moyennes pondérées de ceux de leurs descendants.
+pub fn calculer_totaux(évaluations: &Forêt<Item>, notes: &HashMap<i64, f64>, politique: NoteManquante) -> Result<HashMap<i64, Agrégat>> {
+    let mut totaux = HashMap::new();
+
+    for racine in évaluations.racines() {
+        évaluations.agréger(racine, |_, item, descendants: Vec<(f64, Agrégat)>| {
+            let agrégat = if descendants.is_empty() {
+                match (notes.get(&item.id), politique) {
+                    (Some(&n), _) => Agrégat { valeur: Some(n), complet: true },
+                    (None, NoteManquante::Zéro) => Agrégat { valeur: Some(0.), complet: false },
+                    (None, _) => Agrégat { valeur: None, complet: false }
+                }
+            } else {
+                moyenne_pondérée(&descendants, politique)
+            };
+
+            totaux.insert(item.id, agrégat);
+            (item.pondération, agrégat)
+        })?;
+    }
+
+    Ok(totaux)
+}
+
+pub fn obtenir_notes(conn: &Connection, id_élève: i64) -> Result<HashMap<i64, f64>> {
+    let mut stmt = conn.prepare("
+        SELECT id_item, COALESCE(résultat, résultat_auto)
+            FROM évaluation_résultat
+            WHERE id_élève = ?1 AND id_reprise IS NULL;
+    ")?;
+    let notes = stmt
+        .query_map(params![id_élève], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(notes)
+}
+
+pub fn obtenir_échelle(conn: &Connection, nom: &str) -> Result<Option<Échelle>> {
+    let échelle = conn.query_row("
+        SELECT id, nom, précision, min, max FROM échelle WHERE nom = ?1;
+    ", [nom], |r| Ok((r.get::<_, i64>(0)?, Échelle { nom: r.get(1)?, précision: r.get(2)?, min: r.get(3)?, max: r.get(4)?, niveaux: Vec::new() })))
+        .optional()?;
+
+    match échelle {
+        Some((id_échelle, mut échelle)) => {
+            échelle.niveaux = conn
+                .prepare("
+                    SELECT nom, min, max FROM échelle_niveau WHERE id_échelle = ?1 ORDER BY min;
+                ")?
+                .query_map([id_échelle], |r| Ok(Niveau { nom: r.get(0)?, min: r.get(1)?, max: r.get(2)? }))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(Some(échelle))
+        },
+        None => Ok(None)
+    }
+}
+
+pub fn exporter_structure(conn: &Connection, code: &str, id_session: i64) -> Result<Structure> {
+    let id_cours = trouver_cours(conn, code, id_session)?
+        .ok_or_else(|| Erreur::Structure(format!("Le cours {} est introuvable.", code)))?;
+
+    let évaluations = charger_évaluations(conn, id_cours)?.convertir(|item| ItemStructure {
+        nom: item.nom.clone(),
+        pondération: item.pondération,
+        échelle: item.échelle.clone(),
+        formule: item.formule.clone()
+    });
+
+    let mut échelles = Vec::new();
+    let mut stmt = conn.prepare("
+        SELECT id, nom, précision, min, max
+            FROM échelle
+            WHERE id IN (SELECT id_échelle FROM évaluation_item WHERE id_cours = ?1)
+            ORDER BY nom;
+    ")?;
+    let mut rangées = stmt.query([id_cours])?;
+    while let Some(r) = rangées.next()? {
+        let id_échelle: i64 = r.get(0)?;
+        let niveaux = conn
+            .prepare("
+                SELECT nom, min, max FROM échelle_niveau WHERE id_échelle = ?1 ORDER BY min;
+            ")?
+            .query_map([id_échelle], |r| Ok(Niveau { nom: r.get(0)?, min: r.get(1)?, max: r.get(2)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        échelles.push(Échelle {
+            nom: r.get(1)?,
+            précision: r.get(2)?,
+            min: r.get(3)?,
+            max: r.get(4)?,
+            niveaux
+        });
+    }
+
+    Ok(Structure {
+        cours: code.to_string(),
+        échelles,
+        évaluations
+    })
+}
+
+// Remplace les évaluations du cours par celles de la structure. Les échelles existantes sont réutilisées selon leur nom.
+pub fn importer_structure(conn: &mut Connection, structure: &Structure, id_session: i64) -> Result<()> {
+    let tx = conn.transaction()?;
+
+    let id_cours = trouver_cours(&tx, &structure.cours, id_session)?
+        .ok_or_else(|| Erreur::Structure(format!("Le cours {} est introuvable.", structure.cours)))?;
+
+    let n_résultats: i64 = tx.query_row("
+        SELECT COUNT(*)
+            FROM évaluation_résultat
+            JOIN évaluation_item ON évaluation_item.id = évaluation_résultat.id_item
+            WHERE évaluation_item.id_cours = ?1;
+    ", [id_cours], |r| r.get(0))?;
+    if n_résultats > 0 {
+        return Err(Erreur::Structure(format!("Le cours {} a déjà des résultats d'évaluation.", structure.cours)));
+    }
+
+    tx.execute("
+        DELETE FROM évaluation_item WHERE id_cours = ?1;
+    ", [id_cours])?;
+
+    let mut ids_échelles = HashMap::new();
+    for échelle in &structure.échelles {
+        let id_échelle = tx.query_row("
+            SELECT id FROM échelle WHERE nom = ?1;
+        ", [&échelle.nom], |r| r.get(0)).optional()?;
+        let id_échelle = match id_échelle {
+            Some(id_échelle) => id_échelle,
+            None => {
+                tx.execute("
+                    INSERT INTO échelle(nom, précision, min, max) VALUES (?1, ?2, ?3, ?4);
+                ", params![échelle.nom, échelle.précision, échelle.min, échelle.max])?;
+                let id_échelle = tx.last_insert_rowid();
+
+                for niveau in &échelle.niveaux {
+                    tx.execute("
+                        INSERT INTO échelle_niveau(id_échelle, nom, min, max) VALUES (?1, ?2, ?3, ?4);
+                    ", params![id_échelle, niveau.nom, niveau.min, niveau.max])?;
+                }
+                id_échelle
+            }
+        };
+        ids_échelles.insert(échelle.nom.as_str(), id_échelle);
+    }
+
+    let évaluations = &structure.évaluations;
+    let mut ids = HashMap::new();
+    let mut indices: HashMap<Option<i64>, i64> = HashMap::new();
+    for racine in évaluations.racines() {
+        for id_noeud in évaluations.parcours_préfixe(racine)? {
+            let item = évaluations.val(id_noeud)?;
+            let id_parent = évaluations.ascendant(id_noeud)?.map(|a| ids[&a]);
+
+            // Seules les évaluations, à la racine, ont une échelle et une formule.
+            let id_échelle = match &item.échelle {
+                Some(_) if id_parent.is_some() => {
+                    return Err(Erreur::Structure(format!("L'item {} ne peut pas avoir d'échelle.", item.nom)));
+                },
+                Some(nom) => match ids_échelles.get(nom.as_str()) {
+                    Some(&id_échelle) => Some(id_échelle),
+                    None => tx
+                        .query_row("SELECT id FROM échelle WHERE nom = ?1;", [nom], |r| r.get(0))
+                        .optional()?
+                        .ok_or_else(|| Erreur::Structure(format!("L'échelle {} est introuvable.", nom)))
+                        .map(Some)?
+                },
+                None if item.formule.is_some() => {
+                    return Err(Erreur::Structure(format!("L'item {} a une formule sans échelle.", item.nom)));
+                },
+                None => None
+            };
+
+            let indice = indices.entry(id_parent).or_default();
+            tx.execute("
+                INSERT INTO évaluation_item(nom, id_cours, id_parent, indice, id_échelle, formule, pondération)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+            ", params![item.nom, id_cours, id_parent, *indice, id_échelle, item.formule, item.pondération])?;
+            *indice += 1;
+            ids.insert(id_noeud, tx.last_insert_rowid());
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+// Les échelles sont partagées entre les cours et les résultats ne sont pas copiés.
+pub fn copier_structure(conn: &mut Connection, source: (&str, i64), destination: (&str, i64)) -> Result<()> {
+    let mut structure = exporter_structure(conn, source.0, source.1)?;
+    if structure.évaluations.is_empty() {
+        return Err(Erreur::Structure(format!("Le cours {} n'a aucune évaluation.", source.0)));
+    }
+
+    structure.cours = destination.0.to_string();
+    importer_structure(conn, &structure, destination.1)
+}
+
+// Le format du fichier est déterminé par son extension: .json ou .toml.
+pub fn lire_structure(fichier: &Path) -> Result<Structure> {
+    let contenu = std::fs::read_to_string(fichier).contexte(|| format!("Fichier {}", fichier.display()))?;
+    match fichier.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&contenu).contexte(|| format!("Fichier {}", fichier.display())),
+        Some("toml") => toml::from_str(&contenu).contexte(|| format!("Fichier {}", fichier.display())),
+        _ => Err(Erreur::Structure(format!("Le format du fichier {} est inconnu.", fichier.display())))
+    }
+}
+
+pub fn écrire_structure(fichier: &Path, structure: &Structure) -> Result<()> {
+    let contenu = match fichier.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::to_string_pretty(structure)?,
+        // Le passage par toml::Value place les valeurs simples avant les tables, comme l'exige le format.
+        Some("toml") => toml::to_string(&toml::Value::try_from(structure)?)?,
+        _ => return Err(Erreur::Structure(format!("Le format du fichier {} est inconnu.", fichier.display())))
+    };
+    std::fs::write(fichier, contenu).contexte(|| format!("Fichier {}", fichier.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: i64, pondération: f64) -> Item {
+        Item { id, nom: format!("Item {}", id), pondération, échelle: None, formule: None }
+    }
+
+    // Évaluation 1: section 2 (composants 3 et 4, de pondérations 1 et 3) et composant 5; le composant 4 n'a pas de résultat.
+    fn évaluation() -> Forêt<Item> {
+        let mut évaluations = Forêt::new();
+        let racine = évaluations.créer(item(1, 1.));
+        let section = évaluations.ajouter_descendant(racine, item(2, 1.)).unwrap();
+        évaluations.ajouter_descendant(section, item(3, 1.)).unwrap();
+        évaluations.ajouter_descendant(section, item(4, 3.)).unwrap();
+        évaluations.ajouter_descendant(racine, item(5, 1.)).unwrap();
+        évaluations
+    }
+
+    #[test]
+    fn totaux() {
+        let notes: HashMap<i64, f64> = vec![(3, 80.), (5, 50.)].into_iter().collect();
+        let totaux = |politique| {
+            let totaux = calculer_totaux(&évaluation(), &notes, politique).unwrap();
+            (1..=5).map(|id| (totaux[&id].valeur, totaux[&id].complet)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(totaux(NoteManquante::Ignorer), vec![
+            (Some(65.), false), (Some(80.), false), (Some(80.), true), (None, false), (Some(50.), true)
+        ]);
+        assert_eq!(totaux(NoteManquante::Zéro), vec![
+            (Some(35.), false), (Some(20.), false), (Some(80.), true), (Some(0.), false), (Some(50.), true)
+        ]);
+        assert_eq!(totaux(NoteManquante::Incomplet), vec![
+            (None, false), (None, false), (Some(80.), true), (None, false), (Some(50.), true)
+        ]);
+
+        let notes: HashMap<i64, f64> = vec![(3, 80.), (4, 60.), (5, 50.)].into_iter().collect();
+        let totaux = calculer_totaux(&évaluation(), &notes, NoteManquante::Incomplet).unwrap();
+        assert_eq!(totaux[&1], Agrégat { valeur: Some(57.5), complet: true });
+    }
+
+    #[test]
+    fn structure_aller_retour() {
+        let json = serde_json::json!({
+            "cours": "MHF4U",
+            "echelles": [
+                { "nom": "Niveau", "precision": 1, "min": 0.0, "max": 4.0, "niveaux": [
+                    { "nom": "R", "min": 0.0, "max": 1.0 },
+                    { "nom": "4", "min": 4.0, "max": 4.0 }
+                ] },
+                { "nom": "Pourcentage", "precision": 0, "min": 0.0, "max": 100.0 }
+            ],
+            "evaluations": [
+                { "nom": "Test 1", "ponderation": 2.0, "echelle": "Pourcentage", "formule": "max(a, b)", "descendants": [
+                    { "nom": "Connaissance", "ponderation": 1.0, "echelle": "Niveau", "descendants": [
+                        { "nom": "Question 1", "ponderation": 0.5 },
+                        { "nom": "Question 2", "ponderation": 1.5, "formule": "q1 * 2" }
+                    ] },
+                    { "nom": "Application", "ponderation": 3.0 }
+                ] },
+                { "nom": "Examen", "ponderation": 1.0, "echelle": "Pourcentage" }
+            ]
+        });
+
+        let répertoire = std::env::temp_dir().join(format!("contacteur_structure_{}", std::process::id()));
+        std::fs::create_dir_all(&répertoire).unwrap();
+        let (fichier_json, fichier_toml) = (répertoire.join("structure.json"), répertoire.join("structure.toml"));
+        std::fs::write(&fichier_json, json.to_string()).unwrap();
+
+        écrire_structure(&fichier_toml, &lire_structure(&fichier_json).unwrap()).unwrap();
+        écrire_structure(&fichier_json, &lire_structure(&fichier_toml).unwrap()).unwrap();
+        let retour: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&fichier_json).unwrap()).unwrap();
+        std::fs::remove_dir_all(&répertoire).unwrap();
+
+        assert_eq!(retour, json);
+    }
+
+    #[test]
+    fn chemins() {
+        let évaluations = évaluation();
+        let chemins: Vec<String> = évaluations
+            .parcours_préfixe(évaluations.racines()[0])
+            .unwrap()
+            .map(|id| chemin(&évaluations, id).unwrap())
+            .collect();
+
+        assert_eq!(chemins, vec![
+            "Item 1", "Item 1 / Item 2", "Item 1 / Item 2 / Item 3", "Item 1 / Item 2 / Item 4", "Item 1 / Item 5"
+        ]);
+    }
+
+    #[test]
+    fn politiques() {
+        assert_eq!(lire_politique("Zéro").unwrap(), NoteManquante::Zéro);
+        assert_eq!(lire_politique(" ignorer ").unwrap(), NoteManquante::Ignorer);
+        assert_eq!(lire_politique("incomplet").unwrap(), NoteManquante::Incomplet);
+        assert!(lire_politique("moyenne").is_err());
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use crate::erreur::{Erreur, Result};
 
 // Inspiré de https://rust-leipzig.github.io/architecture/2016/12/20/idiomatic-trees-in-rust/
 // https://docs.rs/indextree/4.3.1/indextree/index.html
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub struct IdNoeud {
     idx: usize
 }
@@ -40,31 +41,638 @@ impl<T> Forêt<T> {
             voisin_prochain: None,
             descendant_premier: None,
             descendant_dernier: None,
-            val: val
+            val
         });
 
-        IdNoeud { idx: idx }
+        IdNoeud { idx }
     }
 
     fn obtenir_noeud(&self, id: IdNoeud) -> Result<&Noeud<T>> {
         self.noeuds
             .get(&id.idx)
-            .ok_or(Erreur::Arbre("Noeud invalide.".into()))
+            .ok_or(Erreur::NoeudInvalide(id.idx))
     }
 
     fn obtenir_noeud_mut(&mut self, id: IdNoeud) -> Result<&mut Noeud<T>> {
         self.noeuds
             .get_mut(&id.idx)
-            .ok_or(Erreur::Arbre("Noeud invalide.".into()))
+            .ok_or(Erreur::NoeudInvalide(id.idx))
+    }
+
+    pub fn len(&self) -> usize {
+        self.noeuds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.noeuds.is_empty()
+    }
+
+    pub fn contient(&self, id: IdNoeud) -> bool {
+        self.noeuds.contains_key(&id.idx)
+    }
+
+    pub fn val(&self, id: IdNoeud) -> Result<&T> {
+        Ok(&self.obtenir_noeud(id)?.val)
+    }
+
+    pub fn val_mut(&mut self, id: IdNoeud) -> Result<&mut T> {
+        Ok(&mut self.obtenir_noeud_mut(id)?.val)
+    }
+
+    pub fn ascendant(&self, id: IdNoeud) -> Result<Option<IdNoeud>> {
+        Ok(self.obtenir_noeud(id)?.ascendant)
+    }
+
+    pub fn voisin_précédent(&self, id: IdNoeud) -> Result<Option<IdNoeud>> {
+        Ok(self.obtenir_noeud(id)?.voisin_précédent)
+    }
+
+    pub fn voisin_prochain(&self, id: IdNoeud) -> Result<Option<IdNoeud>> {
+        Ok(self.obtenir_noeud(id)?.voisin_prochain)
+    }
+
+    pub fn descendant_premier(&self, id: IdNoeud) -> Result<Option<IdNoeud>> {
+        Ok(self.obtenir_noeud(id)?.descendant_premier)
+    }
+
+    pub fn descendant_dernier(&self, id: IdNoeud) -> Result<Option<IdNoeud>> {
+        Ok(self.obtenir_noeud(id)?.descendant_dernier)
+    }
+
+    // Les racines sont énumérées dans l'ordre de leur création.
+    pub fn racines(&self) -> Vec<IdNoeud> {
+        let mut racines: Vec<_> = self.noeuds
+            .iter()
+            .filter(|(_, n)| n.ascendant.is_none())
+            .map(|(&idx, _)| IdNoeud { idx })
+            .collect();
+        racines.sort_by_key(|id| id.idx);
+        racines
+    }
+
+    pub fn descendants(&self, id: IdNoeud) -> Result<Descendants<'_, T>> {
+        Ok(Descendants {
+            forêt: self,
+            prochain: self.obtenir_noeud(id)?.descendant_premier
+        })
+    }
+
+    pub fn ascendants(&self, id: IdNoeud) -> Result<Ascendants<'_, T>> {
+        Ok(Ascendants {
+            forêt: self,
+            prochain: self.obtenir_noeud(id)?.ascendant
+        })
+    }
+
+    pub fn est_ascendant(&self, id_ascendant: IdNoeud, id: IdNoeud) -> Result<bool> {
+        Ok(self.ascendants(id)?.any(|a| a == id_ascendant))
+    }
+
+    pub fn parcours_préfixe(&self, id: IdNoeud) -> Result<ParcoursPréfixe<'_, T>> {
+        self.obtenir_noeud(id)?;
+        Ok(ParcoursPréfixe {
+            forêt: self,
+            pile: vec![id]
+        })
+    }
+
+    pub fn parcours_postfixe(&self, id: IdNoeud) -> Result<ParcoursPostfixe<'_, T>> {
+        self.obtenir_noeud(id)?;
+        Ok(ParcoursPostfixe {
+            forêt: self,
+            pile: vec![(id, false)]
+        })
+    }
+
+    pub fn parcours_largeur(&self, id: IdNoeud) -> Result<ParcoursLargeur<'_, T>> {
+        self.obtenir_noeud(id)?;
+        Ok(ParcoursLargeur {
+            forêt: self,
+            file: VecDeque::from(vec![id])
+        })
+    }
+
+    // Produit une forêt de même forme dont les identifiants de noeuds demeurent valides.
+    pub fn convertir<U, F>(&self, mut f: F) -> Forêt<U>
+    where
+        F: FnMut(&T) -> U {
+        Forêt {
+            prochain_idx: self.prochain_idx,
+            noeuds: self.noeuds
+                .iter()
+                .map(|(&idx, n)| (idx, Noeud {
+                    ascendant: n.ascendant,
+                    voisin_précédent: n.voisin_précédent,
+                    voisin_prochain: n.voisin_prochain,
+                    descendant_premier: n.descendant_premier,
+                    descendant_dernier: n.descendant_dernier,
+                    val: f(&n.val)
+                }))
+                .collect()
+        }
+    }
+
+    // Calcule une valeur pour chaque noeud à partir de celles de ses descendants, des feuilles vers la racine.
+    pub fn agréger<A, F>(&self, id: IdNoeud, mut f: F) -> Result<A>
+    where
+        F: FnMut(IdNoeud, &T, Vec<A>) -> A {
+        let mut valeurs: HashMap<usize, Vec<A>> = HashMap::new();
+        for id_noeud in self.parcours_postfixe(id)? {
+            let noeud = self.obtenir_noeud(id_noeud)?;
+            let descendants = valeurs.remove(&id_noeud.idx).unwrap_or_default();
+            let valeur = f(id_noeud, &noeud.val, descendants);
+
+            if id_noeud == id {
+                return Ok(valeur);
+            }
+
+            match noeud.ascendant {
+                Some(ascendant) => valeurs.entry(ascendant.idx).or_default().push(valeur),
+                None => return Ok(valeur)
+            }
+        }
+
+        Err(Erreur::NoeudInvalide(id.idx))
+    }
+
+    // Retire le noeud de son ascendant et de ses voisins. Le noeud et ses descendants deviennent un arbre distinct.
+    pub fn détacher(&mut self, id: IdNoeud) -> Result<()> {
+        let (ascendant, précédent, prochain) = {
+            let noeud = self.obtenir_noeud_mut(id)?;
+            (noeud.ascendant.take(), noeud.voisin_précédent.take(), noeud.voisin_prochain.take())
+        };
+
+        if let Some(précédent) = précédent {
+            self.obtenir_noeud_mut(précédent)?.voisin_prochain = prochain;
+        } else if let Some(ascendant) = ascendant {
+            self.obtenir_noeud_mut(ascendant)?.descendant_premier = prochain;
+        }
+
+        if let Some(prochain) = prochain {
+            self.obtenir_noeud_mut(prochain)?.voisin_précédent = précédent;
+        } else if let Some(ascendant) = ascendant {
+            self.obtenir_noeud_mut(ascendant)?.descendant_dernier = précédent;
+        }
+
+        Ok(())
+    }
+
+    // Retire le noeud et tous ses descendants de la forêt et retourne leurs valeurs en ordre préfixe.
+    pub fn retirer(&mut self, id: IdNoeud) -> Result<Vec<T>> {
+        self.détacher(id)?;
+
+        let ids: Vec<_> = self.parcours_préfixe(id)?.collect();
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| self.noeuds.remove(&id.idx))
+            .map(|n| n.val)
+            .collect())
+    }
+
+    fn vérifier_déplacement(&self, id_destination: IdNoeud, id: IdNoeud) -> Result<()> {
+        self.obtenir_noeud(id)?;
+        if id_destination == id || self.est_ascendant(id, id_destination)? {
+            return Err(Erreur::NoeudCyclique(id.idx));
+        }
+        Ok(())
     }
 
     pub fn ajouter_descendant(&mut self, id_ascendant: IdNoeud, val: T) -> Result<IdNoeud> {
+        self.obtenir_noeud(id_ascendant)?;
         let id = self.créer(val);
-        let vieux_descendant_dernier = self.obtenir_noeud_mut(id_ascendant)?.descendant_dernier.replace(id);
+        self.annexer_descendant(id_ascendant, id)?;
+        Ok(id)
+    }
+
+    // Déplace le noeud, avec ses descendants, à la fin des descendants d'un autre noeud.
+    pub fn annexer_descendant(&mut self, id_ascendant: IdNoeud, id: IdNoeud) -> Result<()> {
+        self.vérifier_déplacement(id_ascendant, id)?;
+        self.détacher(id)?;
+
+        let vieux_descendant_dernier = {
+            let ascendant = self.obtenir_noeud_mut(id_ascendant)?;
+            let dernier = ascendant.descendant_dernier.replace(id);
+            if dernier.is_none() {
+                ascendant.descendant_premier = Some(id);
+            }
+            dernier
+        };
+
         if let Some(dernier) = vieux_descendant_dernier {
-            self.noeuds.get_mut(&dernier.idx).unwrap().voisin_prochain = Some(id);
-            self.noeuds.get_mut(&id.idx).unwrap().voisin_précédent = Some(dernier);
+            self.obtenir_noeud_mut(dernier)?.voisin_prochain = Some(id);
+        }
+
+        let noeud = self.obtenir_noeud_mut(id)?;
+        noeud.ascendant = Some(id_ascendant);
+        noeud.voisin_précédent = vieux_descendant_dernier;
+
+        Ok(())
+    }
+
+    // Déplace le noeud, avec ses descendants, au début des descendants d'un autre noeud.
+    pub fn préfixer_descendant(&mut self, id_ascendant: IdNoeud, id: IdNoeud) -> Result<()> {
+        match self.descendant_premier(id_ascendant)? {
+            Some(premier) => self.insérer_avant(premier, id),
+            None => self.annexer_descendant(id_ascendant, id)
+        }
+    }
+
+    // Déplace le noeud, avec ses descendants, immédiatement avant un voisin.
+    pub fn insérer_avant(&mut self, id_voisin: IdNoeud, id: IdNoeud) -> Result<()> {
+        self.vérifier_déplacement(id_voisin, id)?;
+        self.détacher(id)?;
+
+        let (ascendant, précédent) = {
+            let voisin = self.obtenir_noeud_mut(id_voisin)?;
+            (voisin.ascendant, voisin.voisin_précédent.replace(id))
+        };
+
+        match précédent {
+            Some(précédent) => self.obtenir_noeud_mut(précédent)?.voisin_prochain = Some(id),
+            None => if let Some(ascendant) = ascendant {
+                self.obtenir_noeud_mut(ascendant)?.descendant_premier = Some(id);
+            }
+        }
+
+        let noeud = self.obtenir_noeud_mut(id)?;
+        noeud.ascendant = ascendant;
+        noeud.voisin_précédent = précédent;
+        noeud.voisin_prochain = Some(id_voisin);
+
+        Ok(())
+    }
+
+    // Déplace le noeud, avec ses descendants, immédiatement après un voisin.
+    pub fn insérer_après(&mut self, id_voisin: IdNoeud, id: IdNoeud) -> Result<()> {
+        self.vérifier_déplacement(id_voisin, id)?;
+        self.détacher(id)?;
+
+        let (ascendant, prochain) = {
+            let voisin = self.obtenir_noeud_mut(id_voisin)?;
+            (voisin.ascendant, voisin.voisin_prochain.replace(id))
+        };
+
+        match prochain {
+            Some(prochain) => self.obtenir_noeud_mut(prochain)?.voisin_précédent = Some(id),
+            None => if let Some(ascendant) = ascendant {
+                self.obtenir_noeud_mut(ascendant)?.descendant_dernier = Some(id);
+            }
+        }
+
+        let noeud = self.obtenir_noeud_mut(id)?;
+        noeud.ascendant = ascendant;
+        noeud.voisin_précédent = Some(id_voisin);
+        noeud.voisin_prochain = prochain;
+
+        Ok(())
+    }
+}
+
+impl<T> Default for Forêt<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Une forêt est représentée par la liste de ses racines; les champs de chaque valeur sont accompagnés de la liste de
+// ses descendants.
+#[derive(Serialize)]
+struct NoeudSérialisé<'a, T> {
+    #[serde(flatten)]
+    val: &'a T,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    descendants: Vec<NoeudSérialisé<'a, T>>
+}
+
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+struct NoeudDésérialisé<T> {
+    #[serde(flatten)]
+    val: T,
+    #[serde(default)]
+    descendants: Vec<NoeudDésérialisé<T>>
+}
+
+impl<T> Forêt<T> {
+    fn sérialiser_noeud(&self, id: IdNoeud) -> NoeudSérialisé<'_, T> {
+        NoeudSérialisé {
+            val: &self.noeuds[&id.idx].val,
+            descendants: Descendants { forêt: self, prochain: self.noeuds[&id.idx].descendant_premier }
+                .map(|d| self.sérialiser_noeud(d))
+                .collect()
         }
-        Ok(id)
     }
-}
\ No newline at end of file
+
+    fn désérialiser_noeud(&mut self, id_ascendant: Option<IdNoeud>, noeud: NoeudDésérialisé<T>) -> Result<()> {
+        let id = match id_ascendant {
+            Some(id_ascendant) => self.ajouter_descendant(id_ascendant, noeud.val)?,
+            None => self.créer(noeud.val)
+        };
+        for descendant in noeud.descendants {
+            self.désérialiser_noeud(Some(id), descendant)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Serialize> Serialize for Forêt<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.racines()
+            .into_iter()
+            .map(|racine| self.sérialiser_noeud(racine))
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Forêt<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut forêt = Forêt::new();
+        for racine in Vec::<NoeudDésérialisé<T>>::deserialize(deserializer)? {
+            forêt.désérialiser_noeud(None, racine).map_err(de::Error::custom)?;
+        }
+
+        Ok(forêt)
+    }
+}
+
+pub struct Descendants<'a, T> {
+    forêt: &'a Forêt<T>,
+    prochain: Option<IdNoeud>
+}
+
+impl<'a, T> Iterator for Descendants<'a, T> {
+    type Item = IdNoeud;
+
+    fn next(&mut self) -> Option<IdNoeud> {
+        let id = self.prochain?;
+        self.prochain = self.forêt.noeuds.get(&id.idx).and_then(|n| n.voisin_prochain);
+        Some(id)
+    }
+}
+
+pub struct Ascendants<'a, T> {
+    forêt: &'a Forêt<T>,
+    prochain: Option<IdNoeud>
+}
+
+impl<'a, T> Iterator for Ascendants<'a, T> {
+    type Item = IdNoeud;
+
+    fn next(&mut self) -> Option<IdNoeud> {
+        let id = self.prochain?;
+        self.prochain = self.forêt.noeuds.get(&id.idx).and_then(|n| n.ascendant);
+        Some(id)
+    }
+}
+
+pub struct ParcoursPréfixe<'a, T> {
+    forêt: &'a Forêt<T>,
+    pile: Vec<IdNoeud>
+}
+
+impl<'a, T> Iterator for ParcoursPréfixe<'a, T> {
+    type Item = IdNoeud;
+
+    fn next(&mut self) -> Option<IdNoeud> {
+        let id = self.pile.pop()?;
+        let n = self.pile.len();
+        self.pile.extend(Descendants { forêt: self.forêt, prochain: self.forêt.noeuds[&id.idx].descendant_premier });
+        self.pile[n..].reverse();
+        Some(id)
+    }
+}
+
+pub struct ParcoursPostfixe<'a, T> {
+    forêt: &'a Forêt<T>,
+    pile: Vec<(IdNoeud, bool)>
+}
+
+impl<'a, T> Iterator for ParcoursPostfixe<'a, T> {
+    type Item = IdNoeud;
+
+    fn next(&mut self) -> Option<IdNoeud> {
+        loop {
+            let (id, visité) = self.pile.pop()?;
+            if visité {
+                return Some(id);
+            }
+
+            self.pile.push((id, true));
+            let n = self.pile.len();
+            self.pile.extend(
+                Descendants { forêt: self.forêt, prochain: self.forêt.noeuds[&id.idx].descendant_premier }
+                    .map(|d| (d, false))
+            );
+            self.pile[n..].reverse();
+        }
+    }
+}
+
+pub struct ParcoursLargeur<'a, T> {
+    forêt: &'a Forêt<T>,
+    file: VecDeque<IdNoeud>
+}
+
+impl<'a, T> Iterator for ParcoursLargeur<'a, T> {
+    type Item = IdNoeud;
+
+    fn next(&mut self) -> Option<IdNoeud> {
+        let id = self.file.pop_front()?;
+        self.file.extend(Descendants { forêt: self.forêt, prochain: self.forêt.noeuds[&id.idx].descendant_premier });
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a
+    // ├── b
+    // │   ├── d
+    // │   └── e
+    // └── c
+    //     └── f
+    fn exemple() -> (Forêt<&'static str>, HashMap<&'static str, IdNoeud>) {
+        let mut forêt = Forêt::new();
+        let a = forêt.créer("a");
+        let b = forêt.ajouter_descendant(a, "b").unwrap();
+        let c = forêt.ajouter_descendant(a, "c").unwrap();
+        let d = forêt.ajouter_descendant(b, "d").unwrap();
+        let e = forêt.ajouter_descendant(b, "e").unwrap();
+        let f = forêt.ajouter_descendant(c, "f").unwrap();
+
+        let ids = vec![("a", a), ("b", b), ("c", c), ("d", d), ("e", e), ("f", f)].into_iter().collect();
+        (forêt, ids)
+    }
+
+    fn valeurs<I: Iterator<Item = IdNoeud>>(forêt: &Forêt<&'static str>, ids: I) -> Vec<&'static str> {
+        ids.map(|id| *forêt.val(id).unwrap()).collect()
+    }
+
+    #[test]
+    fn liens() {
+        let (forêt, ids) = exemple();
+
+        assert_eq!(forêt.ascendant(ids["a"]).unwrap(), None);
+        assert_eq!(forêt.ascendant(ids["b"]).unwrap(), Some(ids["a"]));
+        assert_eq!(forêt.ascendant(ids["f"]).unwrap(), Some(ids["c"]));
+        assert_eq!(forêt.descendant_premier(ids["a"]).unwrap(), Some(ids["b"]));
+        assert_eq!(forêt.descendant_dernier(ids["a"]).unwrap(), Some(ids["c"]));
+        assert_eq!(forêt.voisin_précédent(ids["e"]).unwrap(), Some(ids["d"]));
+        assert_eq!(forêt.voisin_prochain(ids["d"]).unwrap(), Some(ids["e"]));
+        assert_eq!(forêt.voisin_précédent(ids["d"]).unwrap(), None);
+        assert_eq!(forêt.voisin_prochain(ids["e"]).unwrap(), None);
+        assert_eq!(valeurs(&forêt, forêt.descendants(ids["b"]).unwrap()), vec!["d", "e"]);
+        assert_eq!(valeurs(&forêt, forêt.ascendants(ids["e"]).unwrap()), vec!["b", "a"]);
+        assert!(forêt.est_ascendant(ids["a"], ids["f"]).unwrap());
+        assert!(!forêt.est_ascendant(ids["b"], ids["f"]).unwrap());
+    }
+
+    #[test]
+    fn parcours() {
+        let (forêt, ids) = exemple();
+
+        assert_eq!(valeurs(&forêt, forêt.parcours_préfixe(ids["a"]).unwrap()), vec!["a", "b", "d", "e", "c", "f"]);
+        assert_eq!(valeurs(&forêt, forêt.parcours_postfixe(ids["a"]).unwrap()), vec!["d", "e", "b", "f", "c", "a"]);
+        assert_eq!(valeurs(&forêt, forêt.parcours_largeur(ids["a"]).unwrap()), vec!["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(valeurs(&forêt, forêt.parcours_préfixe(ids["b"]).unwrap()), vec!["b", "d", "e"]);
+        assert_eq!(valeurs(&forêt, forêt.parcours_postfixe(ids["f"]).unwrap()), vec!["f"]);
+    }
+
+    #[test]
+    fn racines() {
+        let (mut forêt, ids) = exemple();
+        let g = forêt.créer("g");
+
+        assert_eq!(forêt.racines(), vec![ids["a"], g]);
+
+        forêt.détacher(ids["c"]).unwrap();
+        assert_eq!(forêt.racines(), vec![ids["a"], ids["c"], g]);
+    }
+
+    #[test]
+    fn détacher() {
+        let (mut forêt, ids) = exemple();
+
+        forêt.détacher(ids["b"]).unwrap();
+        assert_eq!(forêt.ascendant(ids["b"]).unwrap(), None);
+        assert_eq!(forêt.voisin_prochain(ids["b"]).unwrap(), None);
+        assert_eq!(forêt.voisin_précédent(ids["c"]).unwrap(), None);
+        assert_eq!(forêt.descendant_premier(ids["a"]).unwrap(), Some(ids["c"]));
+        assert_eq!(valeurs(&forêt, forêt.parcours_préfixe(ids["b"]).unwrap()), vec!["b", "d", "e"]);
+
+        forêt.détacher(ids["f"]).unwrap();
+        assert_eq!(forêt.descendant_premier(ids["c"]).unwrap(), None);
+        assert_eq!(forêt.descendant_dernier(ids["c"]).unwrap(), None);
+    }
+
+    #[test]
+    fn retirer() {
+        let (mut forêt, ids) = exemple();
+
+        assert_eq!(forêt.retirer(ids["b"]).unwrap(), vec!["b", "d", "e"]);
+        assert_eq!(forêt.len(), 3);
+        assert!(!forêt.contient(ids["d"]));
+        assert!(forêt.val(ids["b"]).is_err());
+        assert_eq!(valeurs(&forêt, forêt.parcours_préfixe(ids["a"]).unwrap()), vec!["a", "c", "f"]);
+    }
+
+    #[test]
+    fn insérer() {
+        let (mut forêt, ids) = exemple();
+
+        let g = forêt.créer("g");
+        forêt.insérer_avant(ids["d"], g).unwrap();
+        assert_eq!(valeurs(&forêt, forêt.descendants(ids["b"]).unwrap()), vec!["g", "d", "e"]);
+        assert_eq!(forêt.ascendant(g).unwrap(), Some(ids["b"]));
+
+        let h = forêt.créer("h");
+        forêt.insérer_après(ids["e"], h).unwrap();
+        assert_eq!(valeurs(&forêt, forêt.descendants(ids["b"]).unwrap()), vec!["g", "d", "e", "h"]);
+        assert_eq!(forêt.descendant_dernier(ids["b"]).unwrap(), Some(h));
+
+        forêt.insérer_après(ids["d"], g).unwrap();
+        assert_eq!(valeurs(&forêt, forêt.descendants(ids["b"]).unwrap()), vec!["d", "g", "e", "h"]);
+        assert_eq!(forêt.descendant_premier(ids["b"]).unwrap(), Some(ids["d"]));
+
+        let i = forêt.créer("i");
+        forêt.préfixer_descendant(ids["a"], i).unwrap();
+        assert_eq!(valeurs(&forêt, forêt.descendants(ids["a"]).unwrap()), vec!["i", "b", "c"]);
+    }
+
+    #[test]
+    fn déplacer() {
+        let (mut forêt, ids) = exemple();
+
+        forêt.annexer_descendant(ids["c"], ids["b"]).unwrap();
+        assert_eq!(valeurs(&forêt, forêt.descendants(ids["a"]).unwrap()), vec!["c"]);
+        assert_eq!(valeurs(&forêt, forêt.descendants(ids["c"]).unwrap()), vec!["f", "b"]);
+        assert_eq!(valeurs(&forêt, forêt.parcours_préfixe(ids["a"]).unwrap()), vec!["a", "c", "f", "b", "d", "e"]);
+
+        assert!(forêt.annexer_descendant(ids["d"], ids["c"]).is_err());
+        assert!(forêt.insérer_avant(ids["b"], ids["b"]).is_err());
+        assert!(forêt.insérer_après(ids["e"], ids["a"]).is_err());
+        assert_eq!(valeurs(&forêt, forêt.parcours_préfixe(ids["a"]).unwrap()), vec!["a", "c", "f", "b", "d", "e"]);
+    }
+
+    #[test]
+    fn agréger() {
+        let (forêt, ids) = exemple();
+
+        let chemin = forêt.agréger(ids["a"], |_, val, descendants: Vec<String>| {
+            if descendants.is_empty() {
+                val.to_string()
+            } else {
+                format!("{}({})", val, descendants.join(","))
+            }
+        }).unwrap();
+        assert_eq!(chemin, "a(b(d,e),c(f))");
+
+        let n_feuilles = forêt.agréger(ids["b"], |_, _, descendants: Vec<usize>| {
+            descendants.iter().sum::<usize>().max(1)
+        }).unwrap();
+        assert_eq!(n_feuilles, 2);
+    }
+
+    #[test]
+    fn sérialiser() {
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Item {
+            nom: String
+        }
+
+        let (forêt, ids) = exemple();
+        let forêt = forêt.convertir(|val| Item { nom: val.to_string() });
+        assert_eq!(forêt.val(ids["e"]).unwrap().nom, "e");
+
+        let json = serde_json::to_string(&forêt).unwrap();
+        assert_eq!(json, r#"[{"nom":"a","descendants":[{"nom":"b","descendants":[{"nom":"d"},{"nom":"e"}]},{"nom":"c","descendants":[{"nom":"f"}]}]}]"#);
+
+        let copie: Forêt<Item> = serde_json::from_str(&json).unwrap();
+        let racine = copie.racines()[0];
+        let noms: Vec<_> = copie
+            .parcours_préfixe(racine)
+            .unwrap()
+            .map(|id| copie.val(id).unwrap().nom.as_str())
+            .collect();
+        assert_eq!(noms, vec!["a", "b", "d", "e", "c", "f"]);
+        assert_eq!(serde_json::to_string(&copie).unwrap(), json);
+
+        assert!(serde_json::from_str::<Forêt<Item>>(r#"[{"descendants":[]}]"#).is_err());
+    }
+
+    #[test]
+    fn noeud_invalide() {
+        let (mut forêt, ids) = exemple();
+        forêt.retirer(ids["f"]).unwrap();
+
+        assert!(forêt.ascendant(ids["f"]).is_err());
+        assert!(forêt.descendants(ids["f"]).is_err());
+        assert!(forêt.ajouter_descendant(ids["f"], "g").is_err());
+        assert!(forêt.annexer_descendant(ids["c"], ids["f"]).is_err());
+        assert_eq!(forêt.len(), 5);
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::Write;
+use log::error;
 use rpassword::read_password;
 use crate::erreur::{Erreur, Result};
 
@@ -11,15 +12,9 @@ where
                 return Ok(résultat)
             },
             Result::Err(err) => {
-                println!("Erreur: {}", err);
-                let mut réponse = String::new();
+                error!("{}", err.message_complet());
                 loop {
-                    print!("Essayer à nouveau (o ou n)? ");
-                    std::io::stdout().flush().unwrap();
-                    std::io::stdin().read_line(&mut réponse).unwrap();
-            
-                    let réponse = réponse.trim();
-                    match réponse {
+                    match lire_ligne("Essayer à nouveau (o ou n)? ")?.as_str() {
                         "o" => break,
                         "n" => return Err(Erreur::Abandonné),
                         _ => ()
@@ -30,16 +25,26 @@ where
     }
 }
 
-pub fn obtenir_identifiants(service: &str) -> (String, String) {
+// La fin de l'entrée standard abandonne le travail plutôt que de reposer la question indéfiniment.
+fn lire_ligne(question: &str) -> Result<String> {
+    print!("{}", question);
+    std::io::stdout().flush()?;
+
+    let mut ligne = String::new();
+    if std::io::stdin().read_line(&mut ligne)? == 0 {
+        return Err(Erreur::Abandonné);
+    }
+
+    Ok(ligne.trim().to_string())
+}
+
+pub fn obtenir_identifiants(service: &str) -> Result<(String, String)> {
     println!("***{}***", service);
 
-    let mut utilisateur = String::new();
+    let mut utilisateur;
     loop {
-        print!("Nom d'utilisateur: ");
-        std::io::stdout().flush().unwrap();
-        std::io::stdin().read_line(&mut utilisateur).unwrap();
-        utilisateur = utilisateur.trim().to_string();
-        if utilisateur != "" {
+        utilisateur = lire_ligne("Nom d'utilisateur: ")?;
+        if !utilisateur.is_empty() {
             break
         }
     }
@@ -47,12 +52,32 @@ pub fn obtenir_identifiants(service: &str) -> (String, String) {
     let mut mot_de_passe;
     loop {
         print!("Mot de passe: ");
-        std::io::stdout().flush().unwrap();
-        mot_de_passe = read_password().unwrap();
-        if mot_de_passe != "" {
+        std::io::stdout().flush()?;
+        mot_de_passe = read_password()?;
+        if !mot_de_passe.is_empty() {
             break
         }
     }
 
-    (utilisateur, mot_de_passe)
-}
\ No newline at end of file
+    Ok((utilisateur, mot_de_passe))
+}
+
+// Retire un drapeau des arguments et indique s'il y était (p. ex. -v).
+pub fn extraire_drapeau(args: &mut Vec<String>, drapeau: &str) -> bool {
+    let présent = args.iter().any(|a| a == drapeau);
+    args.retain(|a| a != drapeau);
+    présent
+}
+
+// Retire une option et sa valeur des arguments (p. ex. --session 2026-2027/1).
+pub fn extraire_option(args: &mut Vec<String>, option: &str) -> Result<Option<String>> {
+    match args.iter().position(|a| a == option) {
+        Some(i) if i + 1 < args.len() => {
+            let valeur = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(valeur))
+        },
+        Some(_) => Err(Erreur::Arguments(format!("L'option {} requiert une valeur.", option))),
+        None => Ok(None)
+    }
+}
@@ -1,36 +1,100 @@
-use crate::encompass::{ClientEncompass, Cours};
-use crate::erreur::Result;
+use std::collections::HashMap;
+use crate::encompass::{Absence, Carnet, ClientEncompass, Cours, DonnéesÉlève, Portail};
+use crate::erreur::{Contexte, Result};
 use crate::ilc;
+use log::{debug, info};
 
-pub fn obtenir_contacts() -> Result<Vec<Cours>> {
-    println!("Connexion...");
-    let mut client = ilc::boucler_travail(|| {
-        let (utilisateur, mot_de_passe) = ilc::obtenir_identifiants("EnCompass");
-        ClientEncompass::new(&utilisateur, &mot_de_passe)
+pub fn connecter(portail: &Portail) -> Result<ClientEncompass> {
+    info!("Connexion à {}...", portail.hôte);
+    let client = ilc::boucler_travail(|| {
+        let (utilisateur, mot_de_passe) = ilc::obtenir_identifiants("EnCompass")?;
+        ClientEncompass::new(portail, &utilisateur, &mot_de_passe)
     })?;
-    println!("Connexion réussie!");
+    info!("Connexion réussie!");
 
-    println!("Obtention de la liste des groupes...");
+    Ok(client)
+}
+
+pub fn obtenir_contacts(portail: &Portail) -> Result<Vec<Cours>> {
+    let mut client = connecter(portail)?;
+
+    info!("Obtention de la liste des groupes...");
     let groupes = ilc::boucler_travail(|| client.obtenir_groupes())?;
-    println!("Obtention réussie!");
+    info!("Obtention réussie!");
 
     let mut cours = Vec::new();
     for g in groupes {
-        println!("Obtention des élèves pour {}...", g.code);
-        let élèves_cours = ilc::boucler_travail(|| client.obtenir_élèves_groupe(&g))?;
+        info!("Obtention des élèves pour {}...", g.code);
+        let élèves_cours = ilc::boucler_travail(|| {
+            client.obtenir_élèves_groupe(&g).contexte(|| format!("Groupe {}", g.code))
+        })?;
         cours.extend(élèves_cours);
-        println!("Obtention réussie!")
+        info!("Obtention réussie!")
     }
 
+    // Les données d'un élève inscrit à plusieurs cours ne sont obtenues qu'une fois.
+    let mut données = HashMap::new();
     for c in &mut cours {
+        let code = &c.code;
         for élève in &mut c.élèves {
-            println!("Obtention des contacts pour {} {} {}...", c.code, élève.prénom, élève.nom);
-            let (naissance, contacts_élève) = ilc::boucler_travail(|| client.obtenir_données_élève(élève))?;
-            élève.naissance = naissance;
-            élève.contacts = contacts_élève;
-            println!("Obtention réussie!")
+            if let Some(données_élève) = données.get(&élève.id) {
+                élève.compléter(DonnéesÉlève::clone(données_élève));
+                continue
+            }
+
+            info!("Obtention des contacts pour {} {} {}...", code, élève.prénom, élève.nom);
+            let données_élève = ilc::boucler_travail(|| {
+                client
+                    .obtenir_données_élève(élève)
+                    .contexte(|| format!("Élève {} {} ({}) du cours {}", élève.prénom, élève.nom, élève.id, code))
+            })?;
+            élève.compléter(données_élève.clone());
+            données.insert(élève.id, données_élève);
+            info!("Obtention réussie!")
         }
     }
 
+    debug!("cours={} élèves={} élèves_distincts={}", cours.len(), cours.iter().map(|c| c.élèves.len()).sum::<usize>(), données.len());
+
     Ok(cours)
 }
+
+// Les groupes sont désignés par leur identifiant EnCompass et leur code.
+pub fn obtenir_carnets(client: &ClientEncompass, groupes: &[(i32, String)]) -> Result<Vec<(i32, Carnet)>> {
+    let mut carnets = Vec::new();
+    for (id_groupe, code) in groupes {
+        info!("Obtention du carnet de notes pour {}...", code);
+        let carnet = ilc::boucler_travail(|| {
+            client.obtenir_carnet(*id_groupe).contexte(|| format!("Groupe {}", code))
+        })?;
+        carnets.push((*id_groupe, carnet));
+        info!("Obtention réussie!")
+    }
+
+    Ok(carnets)
+}
+
+pub fn obtenir_absences(client: &ClientEncompass, groupes: &[(i32, String)]) -> Result<Vec<(i32, Vec<Absence>)>> {
+    let mut absences = Vec::new();
+    for (id_groupe, code) in groupes {
+        info!("Obtention des absences pour {}...", code);
+        let absences_groupe = ilc::boucler_travail(|| {
+            client.obtenir_absences(*id_groupe).contexte(|| format!("Groupe {}", code))
+        })?;
+        absences.push((*id_groupe, absences_groupe));
+        info!("Obtention réussie!")
+    }
+
+    Ok(absences)
+}
+
+// Les notes sont données par travail et par élève, selon leurs identifiants EnCompass, sur le total du travail.
+pub fn envoyer_notes(client: &ClientEncompass, id_groupe: i32, code: &str, notes: &[(i32, i32, f64)]) -> Result<()> {
+    info!("Envoi de {} notes pour {}...", notes.len(), code);
+    ilc::boucler_travail(|| {
+        client.envoyer_notes(id_groupe, notes).contexte(|| format!("Groupe {}", code))
+    })?;
+    info!("Envoi réussi!");
+
+    Ok(())
+}
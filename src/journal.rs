@@ -0,0 +1,95 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use crate::erreur::{Contexte, Erreur, Result};
+use crate::ilc;
+
+// Les messages d'information s'affichent tels quels, comme le suivi du travail, alors que le fichier de journal conserve
+// tous les messages jusqu'au niveau de débogage avec l'heure et le module.
+struct Journal {
+    niveau_console: LevelFilter,
+    niveau_fichier: LevelFilter,
+    fichier: Option<Mutex<File>>
+}
+
+// Seuls les messages du logiciel sont journalisés. Ceux des bibliothèques, comme les en-têtes HTTP de reqwest, pourraient
+// contenir des témoins de connexion.
+fn est_local(metadata: &Metadata) -> bool {
+    metadata.target().split("::").next() == module_path!().split("::").next()
+}
+
+impl Log for Journal {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        est_local(metadata) && metadata.level() <= self.niveau_console.max(self.niveau_fichier)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if record.level() <= self.niveau_console {
+            match record.level() {
+                Level::Error => eprintln!("Erreur: {}", record.args()),
+                Level::Warn => eprintln!("Avertissement: {}", record.args()),
+                Level::Info => println!("{}", record.args()),
+                niveau => println!("[{}] {}", niveau, record.args())
+            }
+        }
+
+        if let Some(fichier) = &self.fichier {
+            if record.level() <= self.niveau_fichier {
+                if let Ok(mut fichier) = fichier.lock() {
+                    let _ = writeln!(
+                        fichier,
+                        "{} {:<5} {}: {}",
+                        Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                        record.level(),
+                        record.target(),
+                        record.args()
+                    );
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(Ok(mut fichier)) = self.fichier.as_ref().map(|f| f.lock()) {
+            let _ = fichier.flush();
+        }
+    }
+}
+
+// Retire les options de journalisation des arguments: -q n'affiche que les avertissements et les erreurs, -v ajoute les
+// messages de débogage et -vv les messages de trace. --journal <fichier> ajoute les messages à un fichier.
+pub fn initialiser(args: &mut Vec<String>) -> Result<()> {
+    let silencieux = ilc::extraire_drapeau(args, "-q");
+    let niveau_console = match (silencieux, ilc::extraire_drapeau(args, "-vv"), ilc::extraire_drapeau(args, "-v")) {
+        (true, false, false) => LevelFilter::Warn,
+        (false, true, _) => LevelFilter::Trace,
+        (false, false, true) => LevelFilter::Debug,
+        (false, false, false) => LevelFilter::Info,
+        _ => return Err(Erreur::Arguments("Les options -q et -v ne peuvent pas être combinées.".into()))
+    };
+
+    let fichier = match ilc::extraire_option(args, "--journal")? {
+        Some(chemin) => Some(Mutex::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&chemin)
+                .contexte(|| format!("Fichier {}", chemin))?
+        )),
+        None => None
+    };
+    let niveau_fichier = match fichier {
+        Some(_) => niveau_console.max(LevelFilter::Debug),
+        None => LevelFilter::Off
+    };
+
+    log::set_max_level(niveau_console.max(niveau_fichier));
+    log::set_boxed_logger(Box::new(Journal { niveau_console, niveau_fichier, fichier }))
+        .map_err(|_| Erreur::Arguments("La journalisation est déjà initialisée.".into()))
+}
@@ -0,0 +1,17 @@
+pub mod bd;
+pub mod classeur;
+pub mod connecteurs;
+pub mod coordonnees;
+pub mod encompass;
+pub mod erreur;
+pub mod etiquettes;
+pub mod evaluations;
+pub mod foret;
+pub mod ilc;
+pub mod ilc_encompass;
+pub mod journal;
+pub mod portail;
+pub mod rapports;
+pub mod risques;
+pub mod sessions;
+pub mod statistiques;
@@ -0,0 +1,69 @@
+use rusqlite::Connection;
+use crate::bd::{lire_paramètre, écrire_paramètre};
+use crate::encompass::{est_local, Langue, Portail};
+use crate::erreur::{Erreur, Result};
+
+pub fn lire_langue(langue: &str) -> Result<Langue> {
+    match langue.trim().to_lowercase().as_str() {
+        "fr" | "français" | "french" => Ok(Langue::Français),
+        "en" | "anglais" | "english" => Ok(Langue::Anglais),
+        _ => Err(Erreur::Arguments(format!("La langue {} est invalide (fr ou en).", langue)))
+    }
+}
+
+// L'hôte peut être donné avec ou sans https:// (p. ex. « french.compassforsuccess.ca »). Le préfixe http:// n'est accepté
+// que pour un serveur local (localhost ou 127.0.0.1), p. ex. lors d'essais.
+pub fn lire_hôte(hôte: &str) -> Result<String> {
+    let texte = hôte.trim().trim_end_matches('/');
+    let (préfixe, nom) = match texte.strip_prefix("http://") {
+        Some(nom) => ("http://", nom),
+        None => ("", texte.strip_prefix("https://").unwrap_or(texte))
+    };
+    if nom.is_empty() || nom.contains(|c: char| c.is_whitespace() || c == '/') {
+        return Err(Erreur::Arguments(format!("L'hôte {} est invalide (p. ex. french.compassforsuccess.ca).", hôte)));
+    }
+    if !préfixe.is_empty() && !est_local(nom) {
+        return Err(Erreur::Arguments(format!("L'hôte {} doit utiliser https:// (http:// est réservé à un serveur local).", hôte)));
+    }
+
+    Ok(format!("{}{}", préfixe, nom))
+}
+
+// Les options --langue et --hôte ont préséance sur le portail choisi, puis le portail français est utilisé. Sans hôte, celui
+// du portail de la langue est utilisé.
+pub fn résoudre_portail(conn: &Connection, langue: Option<&str>, hôte: Option<&str>) -> Result<Portail> {
+    let langue = match langue.map(String::from).or(lire_paramètre(conn, "encompass_langue")?) {
+        Some(langue) => lire_langue(&langue)?,
+        None => Langue::Français
+    };
+    let hôte = match hôte.map(String::from).or(lire_paramètre(conn, "encompass_hôte")?) {
+        Some(hôte) => lire_hôte(&hôte)?,
+        None => langue.hôte_défaut().to_string()
+    };
+
+    Ok(Portail { hôte, langue })
+}
+
+pub fn choisir_portail(conn: &Connection, langue: &str, hôte: Option<&str>) -> Result<Portail> {
+    let langue = lire_langue(langue)?;
+    let hôte = hôte.map(lire_hôte).transpose()?;
+
+    écrire_paramètre(conn, "encompass_langue", Some(langue.code()))?;
+    écrire_paramètre(conn, "encompass_hôte", hôte.as_deref())?;
+
+    résoudre_portail(conn, None, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hôtes() {
+        assert_eq!(lire_hôte("https://portail.exemple.ca/").unwrap(), "portail.exemple.ca");
+        assert_eq!(lire_hôte("http://127.0.0.1:8080").unwrap(), "http://127.0.0.1:8080");
+        assert_eq!(lire_hôte("http://localhost:8080/").unwrap(), "http://localhost:8080");
+        assert!(matches!(lire_hôte("http://portail.exemple.ca"), Err(Erreur::Arguments(_))));
+        assert!(matches!(lire_hôte("portail.exemple.ca/portal"), Err(Erreur::Arguments(_))));
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::Local;
+use log::debug;
+use rusqlite::Connection;
+use spreadsheet_ods::{Sheet, write_ods};
+use crate::classeur::nouveau_classeur;
+use crate::erreur::Result;
+use crate::etiquettes::filtrer_élèves;
+
+pub fn exporter_rapport_contacts(conn: &Connection, id_session: i64, étiquettes: &[String]) -> Result<()> {
+    filtrer_élèves(conn, étiquettes)?;
+    let (mut wb, défaut_ref, gras_ref) = nouveau_classeur();
+
+    // Seules les coordonnées valides des contacts qui acceptent la correspondance sont comptées. Pour un élève majeur,
+    // seuls les contacts auxquels il a consenti sont comptés.
+    let mut stmt = conn.prepare("
+        SELECT cours.code, é.prénom, é.nom,
+            (SELECT COUNT(*) FROM élève_contact AS c WHERE c.id_élève = é.id),
+            (SELECT COUNT(*) FROM élève_contact_autorisé AS c WHERE c.id_élève = é.id),
+            (
+                SELECT COUNT(DISTINCT i.normalisée)
+                    FROM élève_contact_autorisé AS c
+                    JOIN élève_contact_item_effectif AS i ON i.id_contact = c.id
+                    JOIN élève_contact_type AS t ON t.id = i.id_type
+                    WHERE c.id_élève = é.id AND i.valide = 1 AND t.type = 'Courriel'
+            ),
+            (
+                SELECT COUNT(DISTINCT i.normalisée)
+                    FROM élève_contact_autorisé AS c
+                    JOIN élève_contact_item_effectif AS i ON i.id_contact = c.id
+                    JOIN élève_contact_type AS t ON t.id = i.id_type
+                    WHERE c.id_élève = é.id AND i.valide = 1 AND t.type <> 'Courriel'
+            ),
+            COALESCE((
+                SELECT GROUP_CONCAT(c.nom_complet, ', ')
+                    FROM élève_contact AS c
+                    WHERE c.id_élève = é.id AND c.correspondance = 0
+            ), ''),
+            COALESCE((
+                SELECT GROUP_CONCAT(i.coordonnée, ', ')
+                    FROM élève_contact_autorisé AS c
+                    JOIN élève_contact_item_effectif AS i ON i.id_contact = c.id
+                    WHERE c.id_élève = é.id AND i.valide = 0
+            ), ''),
+            s.majeur
+            FROM élève AS é
+            JOIN élève_statut AS s ON s.id_élève = é.id
+            JOIN inscription ON inscription.id_élève = é.id
+            LEFT JOIN cours ON cours.id = inscription.id_cours
+            WHERE cours.id_session = ?1
+                AND é.id IN (SELECT id_élève FROM élève_filtré)
+            ORDER BY cours.code, é.prénom, é.nom;")?;
+    let mut req = stmt.query([id_session])?;
+
+    let mut feuille: Option<(String, Sheet, u32)> = None;
+    let mut n_signalés = 0;
+    while let Some(r) = req.next()? {
+        let code: String = r.get(0)?;
+        let n_contacts: u32 = r.get(3)?;
+        let n_correspondance: u32 = r.get(4)?;
+        let n_courriels: u32 = r.get(5)?;
+        let n_téléphones: u32 = r.get(6)?;
+        let majeur: bool = r.get(9)?;
+
+        let mut problèmes = Vec::new();
+        if n_contacts == 0 {
+            problèmes.push("Aucun contact");
+        } else if n_correspondance == 0 && majeur {
+            problèmes.push("Aucun contact autorisé par l'élève majeur");
+        } else if n_correspondance == 0 {
+            problèmes.push("Aucun contact avec correspondance");
+        }
+        if n_courriels == 0 && n_téléphones == 0 {
+            problèmes.push("Aucun tuteur joignable");
+        } else if n_courriels == 0 {
+            problèmes.push("Aucun courriel");
+        } else if n_téléphones == 0 {
+            problèmes.push("Aucun téléphone");
+        }
+        if problèmes.is_empty() {
+            continue
+        }
+
+        if feuille.as_ref().map(|f| &f.0) != Some(&code) {
+            if let Some((_, f, _)) = feuille.take() {
+                wb.push_sheet(f);
+            }
+
+            let mut f = Sheet::new_with_name(&code);
+            ["Prénom", "Nom", "Majeur", "Problèmes", "Correspondance", "Courriels", "Téléphones", "Sans correspondance", "Coordonnées invalides"]
+                .iter()
+                .enumerate()
+                .for_each(|(i, titre)| f.set_styled_value(0, i as u32, *titre, &gras_ref));
+            feuille = Some((code, f, 1));
+        }
+
+        let (_, f, ligne) = feuille.as_mut().unwrap();
+        f.set_styled_value(*ligne, 0, r.get::<_, String>(1)?, &défaut_ref);
+        f.set_styled_value(*ligne, 1, r.get::<_, String>(2)?, &défaut_ref);
+        f.set_styled_value(*ligne, 2, if majeur { "oui" } else { "non" }, &défaut_ref);
+        f.set_styled_value(*ligne, 3, problèmes.join(", "), &défaut_ref);
+        f.set_styled_value(*ligne, 4, n_correspondance, &défaut_ref);
+        f.set_styled_value(*ligne, 5, n_courriels, &défaut_ref);
+        f.set_styled_value(*ligne, 6, n_téléphones, &défaut_ref);
+        f.set_styled_value(*ligne, 7, r.get::<_, String>(7)?, &défaut_ref);
+        f.set_styled_value(*ligne, 8, r.get::<_, String>(8)?, &défaut_ref);
+        *ligne += 1;
+        n_signalés += 1;
+    }
+
+    debug!("diagnostic élèves_signalés={}", n_signalés);
+    match feuille {
+        Some((_, f, _)) => wb.push_sheet(f),
+        None => wb.push_sheet(Sheet::new_with_name("Aucun problème"))
+    }
+
+    let date = Local::now().format("%Y-%m-%d_%H-%M-%S");
+    write_ods(&mut wb, format!["diagnostic_contacts_{}.ods", date])?;
+
+    Ok(())
+}
+
+// Le cumul des absences et des retards de chaque élève par cours, suivi du détail de chaque période.
+pub fn exporter_rapport_absences(conn: &Connection, id_session: i64, étiquettes: &[String]) -> Result<()> {
+    filtrer_élèves(conn, étiquettes)?;
+    let (mut wb, défaut_ref, gras_ref) = nouveau_classeur();
+
+    let mut f_cumul = Sheet::new_with_name("Absences");
+    ["Cours", "Prénom", "Nom", "Absences", "Jours d'absence", "Retards", "Dernière absence"]
+        .iter()
+        .enumerate()
+        .for_each(|(i, titre)| f_cumul.set_styled_value(0, i as u32, *titre, &gras_ref));
+
+    let mut stmt = conn.prepare("
+        SELECT cours.code, é.prénom, é.nom, a.absences, a.jours_absence, a.retards, COALESCE(a.dernière_absence, '')
+            FROM élève AS é
+            JOIN élève_assiduité AS a ON a.id_élève = é.id
+            JOIN cours ON cours.id = a.id_cours
+            WHERE cours.id_session = ?1
+                AND é.id IN (SELECT id_élève FROM élève_filtré)
+            ORDER BY cours.code, a.absences DESC, a.retards DESC, é.prénom, é.nom;")?;
+    let mut req = stmt.query([id_session])?;
+    let mut ligne = 1;
+    while let Some(r) = req.next()? {
+        f_cumul.set_styled_value(ligne, 0, r.get::<_, String>(0)?, &défaut_ref);
+        f_cumul.set_styled_value(ligne, 1, r.get::<_, String>(1)?, &défaut_ref);
+        f_cumul.set_styled_value(ligne, 2, r.get::<_, String>(2)?, &défaut_ref);
+        f_cumul.set_styled_value(ligne, 3, r.get::<_, u32>(3)?, &défaut_ref);
+        f_cumul.set_styled_value(ligne, 4, r.get::<_, u32>(4)?, &défaut_ref);
+        f_cumul.set_styled_value(ligne, 5, r.get::<_, u32>(5)?, &défaut_ref);
+        f_cumul.set_styled_value(ligne, 6, r.get::<_, String>(6)?, &défaut_ref);
+        ligne += 1;
+    }
+    debug!("feuille=Absences rangées={}", ligne - 1);
+
+    let mut f_détail = Sheet::new_with_name("Détail");
+    ["Cours", "Prénom", "Nom", "Date", "Période", "Type"]
+        .iter()
+        .enumerate()
+        .for_each(|(i, titre)| f_détail.set_styled_value(0, i as u32, *titre, &gras_ref));
+
+    let mut stmt = conn.prepare("
+        SELECT cours.code, é.prénom, é.nom, a.date, a.période, CASE WHEN a.retard = 1 THEN 'Retard' ELSE 'Absence' END
+            FROM élève AS é
+            JOIN assiduité AS a ON a.id_élève = é.id
+            JOIN cours ON cours.id = a.id_cours
+            WHERE cours.id_session = ?1
+                AND é.id IN (SELECT id_élève FROM élève_filtré)
+            ORDER BY cours.code, é.prénom, é.nom, a.date, a.période;")?;
+    let mut req = stmt.query([id_session])?;
+    let mut ligne = 1;
+    while let Some(r) = req.next()? {
+        f_détail.set_styled_value(ligne, 0, r.get::<_, String>(0)?, &défaut_ref);
+        f_détail.set_styled_value(ligne, 1, r.get::<_, String>(1)?, &défaut_ref);
+        f_détail.set_styled_value(ligne, 2, r.get::<_, String>(2)?, &défaut_ref);
+        f_détail.set_styled_value(ligne, 3, r.get::<_, String>(3)?, &défaut_ref);
+        f_détail.set_styled_value(ligne, 4, r.get::<_, u32>(4)?, &défaut_ref);
+        f_détail.set_styled_value(ligne, 5, r.get::<_, String>(5)?, &défaut_ref);
+        ligne += 1;
+    }
+    debug!("feuille=Détail rangées={}", ligne - 1);
+
+    wb.push_sheet(f_cumul);
+    wb.push_sheet(f_détail);
+
+    let date = Local::now().format("%Y-%m-%d_%H-%M-%S");
+    write_ods(&mut wb, format!["absences_{}.ods", date])?;
+
+    Ok(())
+}
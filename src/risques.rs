@@ -0,0 +1,569 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use chrono::Local;
+use log::{debug, info, warn};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Deserialize;
+use spreadsheet_ods::{Sheet, write_ods};
+use crate::classeur::nouveau_classeur;
+use crate::erreur::{Contexte, Erreur, Result};
+use crate::etiquettes::filtrer_élèves;
+use crate::evaluations::{calculer_totaux, charger_évaluations, chemin, obtenir_notes, obtenir_échelle, NoteManquante};
+
+// Les règles utilisées en l'absence de fichier. Elles servent aussi de modèle pour un fichier de règles.
+pub const RÈGLES_DÉFAUT: &str = r#"# Un élève est signalé lorsque la somme des priorités des règles qu'il enfreint atteint le seuil.
+seuil = 2
+
+# Un composant dont le résultat est sous le niveau donné de l'échelle de son évaluation.
+[[regle]]
+type = "niveau"
+niveau = "2"
+priorite = 3
+
+# Les résultats des dernières évaluations baissent chaque fois, d'au moins « baisse » points de pourcentage au total.
+[[regle]]
+type = "tendance"
+evaluations = 3
+baisse = 10
+priorite = 2
+
+# Plus de « max » composants sans résultat, parmi ceux déjà évalués pour d'autres élèves du cours.
+[[regle]]
+type = "manquants"
+max = 2
+priorite = 2
+
+# Plus de « max » périodes d'absence ou de retards dans le cours.
+[[regle]]
+type = "absences"
+max = 10
+priorite = 2
+
+[[regle]]
+type = "retards"
+max = 10
+priorite = 1
+
+# Aucun appel réussi aux tuteurs pendant la session.
+[[regle]]
+type = "contact"
+priorite = 1
+
+# Un indicateur du tableau de suivi de la réussite qui a une des valeurs données, p. ex.
+# [[regle]]
+# type = "indicateur"
+# nom = "Élève à risque"
+# valeurs = ["Oui"]
+# priorite = 2
+"#;
+
+fn évaluations_défaut() -> usize {
+    3
+}
+
+fn priorité_défaut() -> u32 {
+    1
+}
+
+fn seuil_défaut() -> u32 {
+    2
+}
+
+// Les clés sont sans accents puisque TOML ne les permet pas sans guillemets.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Critère {
+    Niveau {
+        niveau: String
+    },
+    Tendance {
+        #[serde(rename = "evaluations", alias = "évaluations", default = "évaluations_défaut")]
+        évaluations: usize,
+        #[serde(default)]
+        baisse: f64
+    },
+    Manquants {
+        max: usize
+    },
+    Absences {
+        max: i64
+    },
+    Retards {
+        max: i64
+    },
+    Contact,
+    Indicateur {
+        nom: String,
+        valeurs: Vec<String>
+    }
+}
+
+impl Critère {
+    pub fn nom(&self) -> &'static str {
+        match self {
+            Critère::Niveau { .. } => "niveau",
+            Critère::Tendance { .. } => "tendance",
+            Critère::Manquants { .. } => "manquants",
+            Critère::Absences { .. } => "absences",
+            Critère::Retards { .. } => "retards",
+            Critère::Contact => "contact",
+            Critère::Indicateur { .. } => "indicateur"
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Règle {
+    #[serde(flatten)]
+    pub critère: Critère,
+    #[serde(rename = "priorite", alias = "priorité", default = "priorité_défaut")]
+    pub priorité: u32
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Règles {
+    #[serde(default = "seuil_défaut")]
+    pub seuil: u32,
+    #[serde(rename = "regle", alias = "règle", default)]
+    pub règles: Vec<Règle>
+}
+
+// Le format du fichier est déterminé par son extension: .json ou .toml. Sans fichier, les règles par défaut sont utilisées.
+pub fn lire_règles(fichier: &Path) -> Result<Règles> {
+    if !fichier.exists() {
+        info!("Le fichier {} est absent; les règles par défaut sont utilisées.", fichier.display());
+        return toml::from_str(RÈGLES_DÉFAUT).contexte(|| "Règles par défaut");
+    }
+
+    let contenu = std::fs::read_to_string(fichier).contexte(|| format!("Fichier {}", fichier.display()))?;
+    match fichier.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&contenu).contexte(|| format!("Fichier {}", fichier.display())),
+        Some("toml") => toml::from_str(&contenu).contexte(|| format!("Fichier {}", fichier.display())),
+        _ => Err(Erreur::Arguments(format!("Le format du fichier {} est inconnu.", fichier.display())))
+    }
+}
+
+pub struct Motif {
+    pub règle: &'static str,
+    pub priorité: u32,
+    pub description: String
+}
+
+pub struct Signalement {
+    pub code: String,
+    pub prénom: String,
+    pub nom: String,
+    pub priorité: u32,
+    pub motifs: Vec<Motif>,
+    pub contact: String,
+    pub relation: String,
+    pub téléphone: String,
+    pub conseiller: String
+}
+
+struct Composant {
+    id: i64,
+    chemin: String,
+    racine: usize
+}
+
+struct Racine {
+    id: i64,
+    nom: String,
+    min: f64,
+    max: f64,
+    seuils: Vec<(String, f64)>
+}
+
+// Les évaluations d'un cours et les composants déjà évalués pour au moins un élève.
+struct DonnéesCours {
+    id_cours: i64,
+    racines: Vec<Racine>,
+    composants: Vec<Composant>,
+    évalués: HashSet<i64>
+}
+
+fn nombre(valeur: f64) -> String {
+    format!("{}", (valeur * 100.).round() / 100.)
+}
+
+// Chaque règle enfreinte donne un seul motif, qui en détaille la raison.
+fn évaluer_règle(
+    conn: &Connection, règle: &Règle, id_élève: i64, id_session: i64, cours: &DonnéesCours, notes: &HashMap<i64, f64>,
+    totaux: &HashMap<i64, f64>
+) -> Result<Option<String>> {
+    let DonnéesCours { id_cours, racines, composants, évalués } = cours;
+    let description = match &règle.critère {
+        Critère::Niveau { niveau } => {
+            let sous_niveau: Vec<String> = composants
+                .iter()
+                .filter_map(|c| {
+                    let seuil = racines[c.racine].seuils.iter().find(|(nom, _)| nom == niveau)?.1;
+                    notes.get(&c.id).filter(|n| **n < seuil).map(|n| format!("{} ({})", c.chemin, nombre(*n)))
+                })
+                .collect();
+            if sous_niveau.is_empty() {
+                None
+            } else {
+                Some(format!("Sous le niveau {}: {}", niveau, sous_niveau.join(", ")))
+            }
+        },
+        Critère::Tendance { évaluations, baisse } => {
+            // Les résultats sont comparés en pourcentage de l'échelle de chaque évaluation.
+            let résultats: Vec<(&str, f64)> = racines
+                .iter()
+                .filter(|r| r.max > r.min)
+                .filter_map(|r| totaux.get(&r.id).map(|v| (r.nom.as_str(), (v - r.min) / (r.max - r.min) * 100.)))
+                .collect();
+            let derniers = &résultats[résultats.len().saturating_sub(*évaluations)..];
+            let diminution = derniers.first().zip(derniers.last()).map_or(0., |(premier, dernier)| premier.1 - dernier.1);
+            if *évaluations >= 2 && derniers.len() == *évaluations
+                && derniers.windows(2).all(|p| p[1].1 < p[0].1) && diminution >= *baisse {
+                let détails: Vec<String> = derniers.iter().map(|(nom, v)| format!("{} ({:.0} %)", nom, v)).collect();
+                Some(format!("Baisse de {:.0} points sur {} évaluations: {}", diminution, évaluations, détails.join(", ")))
+            } else {
+                None
+            }
+        },
+        Critère::Manquants { max } => {
+            let manquants: Vec<&str> = composants
+                .iter()
+                .filter(|c| évalués.contains(&c.id) && !notes.contains_key(&c.id))
+                .map(|c| c.chemin.as_str())
+                .collect();
+            if manquants.len() > *max {
+                Some(format!("Résultats manquants ({}): {}", manquants.len(), manquants.join(", ")))
+            } else {
+                None
+            }
+        },
+        Critère::Absences { max } | Critère::Retards { max } => {
+            let (absences, jours, retards): (i64, i64, i64) = conn.query_row("
+                SELECT absences, jours_absence, retards FROM élève_assiduité WHERE id_élève = ?1 AND id_cours = ?2;
+            ", params![id_élève, id_cours], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).optional()?.unwrap_or((0, 0, 0));
+            match règle.critère {
+                Critère::Absences { .. } if absences > *max => Some(format!("{} absences ({} jours)", absences, jours)),
+                Critère::Retards { .. } if retards > *max => Some(format!("{} retards", retards)),
+                _ => None
+            }
+        },
+        Critère::Contact => {
+            let appels: i64 = conn.query_row("
+                SELECT COUNT(*)
+                    FROM élève_appel
+                    JOIN session ON session.id = ?2
+                    WHERE élève_appel.id_élève = ?1 AND élève_appel.réussi = 1
+                        AND élève_appel.date >= COALESCE(session.début, '0000-00-00')
+                        AND élève_appel.date <= COALESCE(session.fin, '9999-12-31');
+            ", params![id_élève, id_session], |r| r.get(0))?;
+            if appels == 0 { Some("Aucun appel réussi aux tuteurs cette session".to_string()) } else { None }
+        },
+        Critère::Indicateur { nom, valeurs } => {
+            // LOWER de SQLite ne convertit pas les lettres accentuées.
+            let indicateurs: Vec<(String, String)> = conn
+                .prepare("SELECT nom, valeur FROM élève_indicateur WHERE id_élève = ?1;")?
+                .query_map([id_élève], |r| Ok((r.get(0)?, r.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            indicateurs
+                .into_iter()
+                .find(|(n, v)| n.to_lowercase() == nom.to_lowercase() && valeurs.iter().any(|attendue| attendue.to_lowercase() == v.to_lowercase()))
+                .map(|(n, v)| format!("{}: {}", n, v))
+        }
+    };
+
+    Ok(description)
+}
+
+// Les élèves de chaque cours dont la priorité atteint le seuil, en ordre décroissant de priorité.
+pub fn détecter_risques(conn: &Connection, id_session: i64, règles: &Règles, étiquettes: &[String]) -> Result<Vec<Signalement>> {
+    filtrer_élèves(conn, étiquettes)?;
+    let cours: Vec<(i64, String)> = conn
+        .prepare("SELECT id, code FROM cours WHERE id_session = ?1 ORDER BY code;")?
+        .query_map([id_session], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    for règle in &règles.règles {
+        if let Critère::Niveau { niveau } = &règle.critère {
+            let existe: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM échelle_niveau WHERE nom = ?1);", [niveau], |r| r.get(0))?;
+            if !existe {
+                warn!("Aucune échelle n'a de niveau « {} »; la règle de niveau ne peut pas être enfreinte.", niveau);
+            }
+        }
+    }
+
+    let mut signalements = Vec::new();
+    for (id_cours, code) in cours {
+        let évaluations = charger_évaluations(conn, id_cours)?;
+        let mut racines = Vec::new();
+        let mut composants = Vec::new();
+        for id_racine in évaluations.racines() {
+            let item = évaluations.val(id_racine)?;
+            let échelle = match &item.échelle {
+                Some(nom) => obtenir_échelle(conn, nom)?,
+                None => None
+            };
+            racines.push(Racine {
+                id: item.id,
+                nom: item.nom.clone(),
+                min: échelle.as_ref().map_or(0., |é| é.min),
+                max: échelle.as_ref().map_or(0., |é| é.max),
+                seuils: échelle.map(|é| é.niveaux.into_iter().map(|n| (n.nom, n.min)).collect()).unwrap_or_default()
+            });
+
+            for id in évaluations.parcours_préfixe(id_racine)? {
+                if évaluations.descendants(id)?.next().is_some() {
+                    continue
+                }
+                composants.push(Composant { id: évaluations.val(id)?.id, chemin: chemin(&évaluations, id)?, racine: racines.len() - 1 });
+            }
+        }
+
+        let évalués = conn
+            .prepare("
+                SELECT DISTINCT évaluation_résultat.id_item
+                    FROM évaluation_résultat
+                    JOIN évaluation_item ON évaluation_item.id = évaluation_résultat.id_item
+                    WHERE évaluation_item.id_cours = ?1 AND évaluation_résultat.id_reprise IS NULL;
+            ")?
+            .query_map([id_cours], |r| r.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let données_cours = DonnéesCours { id_cours, racines, composants, évalués };
+
+        let mut stmt = conn.prepare("
+            SELECT é.id, é.prénom, é.nom, COALESCE(é.conseiller, '')
+                FROM élève AS é
+                JOIN inscription ON inscription.id_élève = é.id
+                WHERE inscription.id_cours = ?1
+                    AND é.id IN (SELECT id_élève FROM élève_filtré)
+                ORDER BY é.prénom, é.nom;
+        ")?;
+        let élèves: Vec<(i64, String, String, String)> = stmt
+            .query_map([id_cours], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut signalements_cours = Vec::new();
+        for (id_élève, prénom, nom, conseiller) in élèves {
+            let notes = obtenir_notes(conn, id_élève)?;
+            let totaux = calculer_totaux(&évaluations, &notes, NoteManquante::Ignorer)?
+                .into_iter()
+                .filter_map(|(id, agrégat)| agrégat.valeur.map(|v| (id, v)))
+                .collect();
+
+            let mut motifs = Vec::new();
+            for règle in &règles.règles {
+                if let Some(description) = évaluer_règle(conn, règle, id_élève, id_session, &données_cours, &notes, &totaux)? {
+                    motifs.push(Motif { règle: règle.critère.nom(), priorité: règle.priorité, description });
+                }
+            }
+
+            let priorité = motifs.iter().map(|m| m.priorité).sum();
+            if motifs.is_empty() || priorité < règles.seuil {
+                continue
+            }
+
+            // Le premier contact autorisé de l'élève, avec son téléphone le plus susceptible de répondre.
+            let (contact, relation, téléphone) = conn.query_row("
+                SELECT c.nom_complet, COALESCE(c.relation, ''), COALESCE((
+                    SELECT i.coordonnée
+                        FROM élève_contact_item_effectif AS i
+                        JOIN élève_contact_type AS t ON t.id = i.id_type
+                        WHERE i.id_contact = c.id AND t.type LIKE 'Téléphone%'
+                        ORDER BY COALESCE(i.valide, 0) DESC,
+                            CASE t.type WHEN 'Téléphone cellulaire' THEN 0 WHEN 'Téléphone au domicile' THEN 1 ELSE 2 END
+                        LIMIT 1
+                ), '')
+                    FROM élève_contact_autorisé AS c
+                    WHERE c.id_élève = ?1
+                    ORDER BY c.ordre IS NULL, c.ordre, c.nom_complet
+                    LIMIT 1;
+            ", [id_élève], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).optional()?.unwrap_or_default();
+
+            signalements_cours.push(Signalement {
+                code: code.clone(),
+                prénom,
+                nom,
+                priorité,
+                motifs,
+                contact,
+                relation,
+                téléphone,
+                conseiller
+            });
+        }
+        signalements_cours.sort_by_key(|s| std::cmp::Reverse(s.priorité));
+        debug!("risques cours={} élèves_signalés={}", code, signalements_cours.len());
+        signalements.extend(signalements_cours);
+    }
+
+    Ok(signalements)
+}
+
+pub fn afficher_risques(signalements: &[Signalement]) {
+    let mut code_précédent = None;
+    for s in signalements {
+        if code_précédent != Some(&s.code) {
+            println!("---{}", s.code);
+            code_précédent = Some(&s.code);
+        }
+
+        let motifs: Vec<&str> = s.motifs.iter().map(|m| m.description.as_str()).collect();
+        println!("[{}] {} {}: {}", s.priorité, s.prénom, s.nom, motifs.join("; "));
+    }
+}
+
+pub fn exporter_risques(signalements: &[Signalement]) -> Result<()> {
+    let (mut wb, défaut_ref, gras_ref) = nouveau_classeur();
+
+    let mut f_élèves = Sheet::new_with_name("Élèves à risque");
+    ["Cours", "Prénom", "Nom", "Priorité", "Motifs", "Contact", "Relation", "Téléphone", "Conseiller"]
+        .iter()
+        .enumerate()
+        .for_each(|(i, titre)| f_élèves.set_styled_value(0, i as u32, *titre, &gras_ref));
+
+    let mut f_motifs = Sheet::new_with_name("Motifs");
+    ["Cours", "Prénom", "Nom", "Règle", "Priorité", "Motif"]
+        .iter()
+        .enumerate()
+        .for_each(|(i, titre)| f_motifs.set_styled_value(0, i as u32, *titre, &gras_ref));
+
+    let mut ligne_motif = 1;
+    for (ligne, s) in signalements.iter().enumerate() {
+        let ligne = ligne as u32 + 1;
+        let motifs: Vec<&str> = s.motifs.iter().map(|m| m.description.as_str()).collect();
+        f_élèves.set_styled_value(ligne, 0, &s.code, &défaut_ref);
+        f_élèves.set_styled_value(ligne, 1, &s.prénom, &défaut_ref);
+        f_élèves.set_styled_value(ligne, 2, &s.nom, &défaut_ref);
+        f_élèves.set_styled_value(ligne, 3, s.priorité, &défaut_ref);
+        f_élèves.set_styled_value(ligne, 4, motifs.join("; "), &défaut_ref);
+        f_élèves.set_styled_value(ligne, 5, &s.contact, &défaut_ref);
+        f_élèves.set_styled_value(ligne, 6, &s.relation, &défaut_ref);
+        f_élèves.set_styled_value(ligne, 7, &s.téléphone, &défaut_ref);
+        f_élèves.set_styled_value(ligne, 8, &s.conseiller, &défaut_ref);
+
+        for motif in &s.motifs {
+            f_motifs.set_styled_value(ligne_motif, 0, &s.code, &défaut_ref);
+            f_motifs.set_styled_value(ligne_motif, 1, &s.prénom, &défaut_ref);
+            f_motifs.set_styled_value(ligne_motif, 2, &s.nom, &défaut_ref);
+            f_motifs.set_styled_value(ligne_motif, 3, motif.règle, &défaut_ref);
+            f_motifs.set_styled_value(ligne_motif, 4, motif.priorité, &défaut_ref);
+            f_motifs.set_styled_value(ligne_motif, 5, &motif.description, &défaut_ref);
+            ligne_motif += 1;
+        }
+    }
+    debug!("feuille=Élèves à risque rangées={}", signalements.len());
+    debug!("feuille=Motifs rangées={}", ligne_motif - 1);
+
+    wb.push_sheet(f_élèves);
+    wb.push_sheet(f_motifs);
+
+    let date = Local::now().format("%Y-%m-%d_%H-%M-%S");
+    write_ods(&mut wb, format!["risques_{}.ods", date])?;
+
+    Ok(())
+}
+
+pub fn noter_appel(conn: &Connection, id_élève: i64, réussi: bool, note: Option<&str>) -> Result<()> {
+    conn.execute("
+        INSERT INTO élève_appel(id_élève, date, réussi, note) VALUES (?1, DATE('now', 'localtime'), ?2, ?3);
+    ", params![id_élève, réussi, note])?;
+
+    Ok(())
+}
+
+pub fn lister_appels(conn: &Connection, id_session: i64) -> Result<Vec<(String, String, bool, String)>> {
+    let appels = conn
+        .prepare("
+            SELECT a.date, é.prénom || ' ' || é.nom, a.réussi, COALESCE(a.note, '')
+                FROM élève_appel AS a
+                JOIN élève AS é ON é.id = a.id_élève
+                JOIN session ON session.id = ?1
+                WHERE a.date >= COALESCE(session.début, '0000-00-00') AND a.date <= COALESCE(session.fin, '9999-12-31')
+                ORDER BY a.date, a.id;
+        ")?
+        .query_map([id_session], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(appels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bd;
+
+    // Trois évaluations sur l'échelle des pourcentages, avec les niveaux créés pour celle-ci; la première a deux
+    // composants, les autres un seul.
+    fn cours() -> DonnéesCours {
+        let seuils = vec![("R".to_string(), 0.), ("1".to_string(), 50.), ("2".to_string(), 60.), ("3".to_string(), 70.), ("4".to_string(), 80.)];
+        let racine = |id: i64, nom: &str| Racine { id, nom: nom.to_string(), min: 0., max: 100., seuils: seuils.clone() };
+        let composant = |id: i64, chemin: &str, racine: usize| Composant { id, chemin: chemin.to_string(), racine };
+
+        DonnéesCours {
+            id_cours: 1,
+            racines: vec![racine(1, "Test 1"), racine(2, "Test 2"), racine(3, "Examen")],
+            composants: vec![composant(11, "Test 1 / A", 0), composant(12, "Test 1 / B", 0), composant(21, "Test 2 / A", 1), composant(31, "Examen / A", 2)],
+            évalués: [11, 12, 21, 31].iter().copied().collect()
+        }
+    }
+
+    fn évaluer(critère: Critère, notes: &[(i64, f64)], totaux: &[(i64, f64)]) -> Option<String> {
+        let conn = bd::ouvrir(None).unwrap();
+        let règle = Règle { critère, priorité: 1 };
+        let notes = notes.iter().copied().collect();
+        let totaux = totaux.iter().copied().collect();
+        évaluer_règle(&conn, &règle, 1, 1, &cours(), &notes, &totaux).unwrap()
+    }
+
+    #[test]
+    fn niveau() {
+        let notes = [(11, 55.), (12, 75.), (21, 59.99), (31, 60.)];
+        assert_eq!(
+            évaluer(Critère::Niveau { niveau: "2".into() }, &notes, &[]).as_deref(),
+            Some("Sous le niveau 2: Test 1 / A (55), Test 2 / A (59.99)")
+        );
+        assert_eq!(évaluer(Critère::Niveau { niveau: "1".into() }, &notes, &[]), None);
+        assert_eq!(évaluer(Critère::Niveau { niveau: "Excellent".into() }, &notes, &[]), None);
+    }
+
+    #[test]
+    fn tendance() {
+        let critère = |évaluations, baisse| Critère::Tendance { évaluations, baisse };
+        let baisse = [(1, 80.), (2, 70.), (3, 55.)];
+
+        assert_eq!(
+            évaluer(critère(3, 10.), &[], &baisse).as_deref(),
+            Some("Baisse de 25 points sur 3 évaluations: Test 1 (80 %), Test 2 (70 %), Examen (55 %)")
+        );
+        assert_eq!(évaluer(critère(2, 10.), &[], &baisse).as_deref(), Some("Baisse de 15 points sur 2 évaluations: Test 2 (70 %), Examen (55 %)"));
+        assert_eq!(évaluer(critère(3, 30.), &[], &baisse), None);
+        assert_eq!(évaluer(critère(3, 10.), &[], &[(1, 80.), (2, 85.), (3, 55.)]), None);
+        assert_eq!(évaluer(critère(3, 10.), &[], &[(1, 80.), (3, 55.)]), None);
+    }
+
+    #[test]
+    fn manquants() {
+        let notes = [(11, 75.), (21, 80.)];
+        assert_eq!(
+            évaluer(Critère::Manquants { max: 1 }, &notes, &[]).as_deref(),
+            Some("Résultats manquants (2): Test 1 / B, Examen / A")
+        );
+        assert_eq!(évaluer(Critère::Manquants { max: 2 }, &notes, &[]), None);
+    }
+
+    #[test]
+    fn règles_défaut() {
+        let règles: Règles = toml::from_str(RÈGLES_DÉFAUT).unwrap();
+        assert_eq!(règles.seuil, seuil_défaut());
+        assert_eq!(règles.règles[0], Règle { critère: Critère::Niveau { niveau: "2".into() }, priorité: 3 });
+
+        let conn = bd::ouvrir(None).unwrap();
+        conn.execute_batch("
+            INSERT INTO échelle(nom, précision, min, max) VALUES ('Niveau', 4, 0, 4), ('Pourcentage', 0, 0, 100);
+        ").unwrap();
+        bd::créer_niveaux(&conn).unwrap();
+        for (nom, min) in &[("Niveau", 2.), ("Pourcentage", 60.)] {
+            let échelle = obtenir_échelle(&conn, nom).unwrap().unwrap();
+            assert_eq!(échelle.niveaux.iter().find(|n| n.nom == "2").map(|n| n.min), Some(*min));
+        }
+    }
+}
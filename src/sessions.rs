@@ -0,0 +1,152 @@
+use chrono::{Datelike, Local, NaiveDate};
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::bd;
+use crate::erreur::{Erreur, Result};
+
+// Une année scolaire commence le 1er septembre et compte deux semestres: de septembre à janvier et de février à juin.
+// Les mois de juillet et d'août sont rattachés au premier semestre de l'année qui suit.
+fn session_pour_date(date: NaiveDate) -> (i32, u32) {
+    match date.month() {
+        1 => (date.year() - 1, 1),
+        2..=6 => (date.year() - 1, 2),
+        _ => (date.year(), 1)
+    }
+}
+
+fn nom_année(année: i32) -> String {
+    format!("{}-{}", année, année + 1)
+}
+
+// Le format attendu est « 2026-2027/1 ».
+pub fn lire_session(session: &str) -> Result<(i32, u32)> {
+    let r_session = Regex::new(r"^([0-9]{4})-([0-9]{4})/([12])$").unwrap();
+
+    let c = r_session
+        .captures(session.trim())
+        .ok_or_else(|| Erreur::Session(format!("La session {} est invalide (p. ex. 2026-2027/1).", session)))?;
+    let année: i32 = c[1].parse().unwrap();
+    if c[2].parse::<i32>().unwrap() != année + 1 {
+        return Err(Erreur::Session(format!("L'année scolaire de la session {} est invalide.", session)));
+    }
+
+    Ok((année, c[3].parse().unwrap()))
+}
+
+pub fn obtenir_session(conn: &Connection, année: i32, semestre: u32) -> Result<i64> {
+    let (début, fin) = match semestre {
+        1 => (NaiveDate::from_ymd_opt(année, 9, 1), NaiveDate::from_ymd_opt(année + 1, 1, 31)),
+        _ => (NaiveDate::from_ymd_opt(année + 1, 2, 1), NaiveDate::from_ymd_opt(année + 1, 6, 30))
+    };
+
+    conn.execute("
+        INSERT OR IGNORE INTO année_scolaire(nom, début, fin) VALUES (?1, ?2, ?3);
+    ", params![nom_année(année), format!("{}-09-01", année), format!("{}-06-30", année + 1)])?;
+    conn.execute("
+        INSERT OR IGNORE INTO session(id_année, semestre, début, fin)
+            SELECT id, ?2, ?3, ?4 FROM année_scolaire WHERE nom = ?1;
+    ", params![nom_année(année), semestre, début.map(|d| d.to_string()), fin.map(|d| d.to_string())])?;
+
+    Ok(conn.query_row("
+        SELECT session.id
+            FROM session
+            JOIN année_scolaire ON année_scolaire.id = session.id_année
+            WHERE année_scolaire.nom = ?1 AND session.semestre = ?2;
+    ", params![nom_année(année), semestre], |r| r.get(0))?)
+}
+
+// La session choisie par l'utilisateur, sinon celle qui comprend la date du jour.
+pub fn session_courante(conn: &Connection) -> Result<i64> {
+    match bd::lire_paramètre(conn, "session_courante")?.and_then(|id| id.parse().ok()) {
+        Some(id_session) => Ok(id_session),
+        None => {
+            let (année, semestre) = session_pour_date(Local::now().date_naive());
+            obtenir_session(conn, année, semestre)
+        }
+    }
+}
+
+pub fn résoudre_session(conn: &Connection, session: Option<&str>) -> Result<i64> {
+    match session {
+        Some(session) => {
+            let (année, semestre) = lire_session(session)?;
+            obtenir_session(conn, année, semestre)
+        },
+        None => session_courante(conn)
+    }
+}
+
+pub fn choisir_session(conn: &Connection, id_session: Option<i64>) -> Result<()> {
+    bd::écrire_paramètre(conn, "session_courante", id_session.map(|id| id.to_string()).as_deref())
+}
+
+pub fn nom_session(conn: &Connection, id_session: i64) -> Result<String> {
+    Ok(conn.query_row("
+        SELECT année_scolaire.nom || '/' || session.semestre
+            FROM session
+            JOIN année_scolaire ON année_scolaire.id = session.id_année
+            WHERE session.id = ?1;
+    ", [id_session], |r| r.get(0))?)
+}
+
+// Retourne (id, nom, nombre de cours) de chaque session, en ordre chronologique.
+pub fn lister_sessions(conn: &Connection) -> Result<Vec<(i64, String, i64)>> {
+    let mut stmt = conn.prepare("
+        SELECT session.id, année_scolaire.nom || '/' || session.semestre,
+            (SELECT COUNT(*) FROM cours WHERE cours.id_session = session.id)
+            FROM session
+            JOIN année_scolaire ON année_scolaire.id = session.id_année
+            ORDER BY année_scolaire.début, session.semestre;
+    ")?;
+    let sessions = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(sessions)
+}
+
+pub fn trouver_cours(conn: &Connection, code: &str, id_session: i64) -> Result<Option<i64>> {
+    Ok(conn.query_row("
+        SELECT id FROM cours WHERE code = ?1 AND id_session = ?2;
+    ", params![code, id_session], |r| r.get(0)).optional()?)
+}
+
+// Une référence de cours est un code, dans la session donnée, ou un code suivi d'une session (p. ex. MPM2D@2025-2026/2).
+pub fn lire_référence_cours(conn: &Connection, référence: &str, id_session: i64) -> Result<(String, i64)> {
+    match référence.split_once('@') {
+        Some((code, session)) => {
+            let (année, semestre) = lire_session(session)?;
+            Ok((code.to_string(), obtenir_session(conn, année, semestre)?))
+        },
+        None => Ok((référence.to_string(), id_session))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_selon_date() {
+        let session = |a, m, j| session_pour_date(NaiveDate::from_ymd_opt(a, m, j).unwrap());
+        assert_eq!(session(2030, 9, 1), (2030, 1));
+        assert_eq!(session(2031, 1, 1), (2030, 1));
+        assert_eq!(session(2031, 1, 31), (2030, 1));
+        assert_eq!(session(2031, 2, 1), (2030, 2));
+        assert_eq!(session(2031, 6, 30), (2030, 2));
+        assert_eq!(session(2031, 7, 1), (2031, 1));
+        assert_eq!(session(2031, 8, 31), (2031, 1));
+    }
+
+    #[test]
+    fn sessions() {
+        assert_eq!(lire_session("2026-2027/2").unwrap(), (2026, 2));
+        assert!(lire_session("2026-2028/1").is_err());
+        assert!(lire_session("2026-2027/3").is_err());
+
+        let conn = bd::ouvrir(None).unwrap();
+        let id_session = obtenir_session(&conn, 2030, 2).unwrap();
+        assert_eq!(obtenir_session(&conn, 2030, 2).unwrap(), id_session);
+        assert_eq!(nom_session(&conn, id_session).unwrap(), "2030-2031/2");
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use chrono::Local;
+use log::debug;
+use rusqlite::Connection;
+use spreadsheet_ods::{Sheet, write_ods};
+use crate::classeur::nouveau_classeur;
+use crate::erreur::Result;
+use crate::etiquettes::filtrer_élèves;
+use crate::evaluations::{calculer_totaux, charger_évaluations, chemin, obtenir_notes, obtenir_échelle, NoteManquante};
+
+pub struct Statistiques {
+    pub n: usize,
+    pub moyenne: f64,
+    pub médiane: f64,
+    pub écart_type: f64,
+    pub min: f64,
+    pub max: f64
+}
+
+// L'écart-type est celui de la population, puisque les résultats sont ceux de tous les élèves du groupe.
+fn calculer(valeurs: &[f64]) -> Option<Statistiques> {
+    if valeurs.is_empty() {
+        return None;
+    }
+
+    let mut triées = valeurs.to_vec();
+    triées.sort_by(f64::total_cmp);
+    let n = triées.len();
+    let moyenne = triées.iter().sum::<f64>() / n as f64;
+    let médiane = if n % 2 == 1 { triées[n / 2] } else { (triées[n / 2 - 1] + triées[n / 2]) / 2. };
+    let variance = triées.iter().map(|v| (v - moyenne).powi(2)).sum::<f64>() / n as f64;
+
+    Some(Statistiques { n, moyenne, médiane, écart_type: variance.sqrt(), min: triées[0], max: triées[n - 1] })
+}
+
+pub struct StatistiquesItem {
+    pub code: String,
+    pub chemin: String,
+    pub nom: String,
+    pub profondeur: usize,
+    pub genre: &'static str,
+    pub valeurs: Vec<f64>,
+    pub statistiques: Option<Statistiques>,
+    pub niveaux: Vec<(String, usize)>
+}
+
+// Les groupes d'un même cours (p. ex. MHF4U-01 et MHF4U-02) partagent le code qui précède le tiret.
+fn code_de_base(code: &str) -> &str {
+    code.split_once('-').map_or(code, |(base, _)| base)
+}
+
+// Les résultats des sections et des évaluations de chaque élève sont calculés à partir des composants qu'il a complétés;
+// un résultat manquant est exclu des statistiques. Un résultat à la limite de deux niveaux compte pour le plus élevé.
+pub fn calculer_statistiques(conn: &Connection, id_session: i64, étiquettes: &[String]) -> Result<Vec<StatistiquesItem>> {
+    filtrer_élèves(conn, étiquettes)?;
+    let cours: Vec<(i64, String)> = conn
+        .prepare("SELECT id, code FROM cours WHERE id_session = ?1 ORDER BY code;")?
+        .query_map([id_session], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut items = Vec::new();
+    for (id_cours, code) in cours {
+        let évaluations = charger_évaluations(conn, id_cours)?;
+        let élèves: Vec<i64> = conn
+            .prepare("
+                SELECT id_élève FROM inscription
+                    WHERE id_cours = ?1
+                        AND id_élève IN (SELECT id_élève FROM élève_filtré);
+            ")?
+            .query_map([id_cours], |r| r.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut valeurs: HashMap<i64, Vec<f64>> = HashMap::new();
+        for id_élève in &élèves {
+            let notes = obtenir_notes(conn, *id_élève)?;
+            for (id_item, agrégat) in calculer_totaux(&évaluations, &notes, NoteManquante::Ignorer)? {
+                if let Some(valeur) = agrégat.valeur {
+                    valeurs.entry(id_item).or_default().push(valeur);
+                }
+            }
+        }
+
+        for racine in évaluations.racines() {
+            let niveaux = match &évaluations.val(racine)?.échelle {
+                Some(échelle) => obtenir_échelle(conn, échelle)?.map(|é| é.niveaux).unwrap_or_default(),
+                None => Vec::new()
+            };
+
+            for id in évaluations.parcours_préfixe(racine)? {
+                let item = évaluations.val(id)?;
+                let profondeur = évaluations.ascendants(id)?.count();
+                let genre = if profondeur == 0 {
+                    "Évaluation"
+                } else if évaluations.descendants(id)?.next().is_none() {
+                    "Composant"
+                } else {
+                    "Section"
+                };
+
+                let valeurs = valeurs.remove(&item.id).unwrap_or_default();
+                let mut répartition: Vec<(String, usize)> = niveaux.iter().map(|n| (n.nom.clone(), 0)).collect();
+                for v in &valeurs {
+                    if let Some(i) = niveaux.iter().rposition(|n| n.min <= *v && *v <= n.max) {
+                        répartition[i].1 += 1;
+                    }
+                }
+
+                items.push(StatistiquesItem {
+                    code: code.clone(),
+                    chemin: chemin(&évaluations, id)?,
+                    nom: item.nom.clone(),
+                    profondeur,
+                    genre,
+                    statistiques: calculer(&valeurs),
+                    valeurs,
+                    niveaux: répartition
+                });
+            }
+        }
+        debug!("statistiques cours={} élèves={} items={}", code, élèves.len(), évaluations.len());
+    }
+
+    Ok(items)
+}
+
+pub struct Comparaison<'a> {
+    pub code: &'a str,
+    pub chemin: &'a str,
+    pub groupes: Vec<&'a StatistiquesItem>,
+    pub moyenne: f64
+}
+
+// Les items de même chemin des groupes d'un même cours sont comparés à la moyenne de l'ensemble de leurs résultats.
+pub fn comparer(items: &[StatistiquesItem]) -> Vec<Comparaison<'_>> {
+    let mut comparaisons: Vec<Comparaison> = Vec::new();
+    for item in items {
+        let code = code_de_base(&item.code);
+        match comparaisons.iter_mut().find(|c| c.code == code && c.chemin == item.chemin) {
+            Some(comparaison) => comparaison.groupes.push(item),
+            None => comparaisons.push(Comparaison { code, chemin: &item.chemin, groupes: vec![item], moyenne: 0. })
+        }
+    }
+
+    for comparaison in &mut comparaisons {
+        let valeurs: Vec<f64> = comparaison.groupes.iter().flat_map(|g| g.valeurs.iter().copied()).collect();
+        comparaison.moyenne = calculer(&valeurs).map_or(0., |s| s.moyenne);
+    }
+    comparaisons.retain(|c| c.groupes.iter().filter(|g| g.statistiques.is_some()).count() > 1);
+
+    comparaisons
+}
+
+fn résumer_niveaux(niveaux: &[(String, usize)]) -> String {
+    niveaux.iter().map(|(nom, n)| format!("{}: {}", nom, n)).collect::<Vec<_>>().join(", ")
+}
+
+pub fn afficher_statistiques(items: &[StatistiquesItem]) {
+    let mut code_précédent = None;
+    for item in items {
+        if code_précédent != Some(&item.code) {
+            println!("---{}", item.code);
+            code_précédent = Some(&item.code);
+        }
+
+        let retrait = "  ".repeat(item.profondeur);
+        match &item.statistiques {
+            Some(s) => {
+                let niveaux = if item.niveaux.is_empty() { String::new() } else { format!(" ({})", résumer_niveaux(&item.niveaux)) };
+                println!(
+                    "{}{}: n={} moyenne={:.1} médiane={:.1} écart-type={:.1} min={:.1} max={:.1}{}",
+                    retrait, item.nom, s.n, s.moyenne, s.médiane, s.écart_type, s.min, s.max, niveaux
+                );
+            },
+            None => println!("{}{}: aucun résultat", retrait, item.nom)
+        }
+    }
+
+    let comparaisons = comparer(items);
+    if !comparaisons.is_empty() {
+        println!("---Comparaison des groupes");
+    }
+    for comparaison in comparaisons {
+        let groupes: Vec<String> = comparaison.groupes
+            .iter()
+            .filter_map(|g| g.statistiques.as_ref().map(|s| format!("{} {:.1} ({:+.1})", g.code, s.moyenne, s.moyenne - comparaison.moyenne)))
+            .collect();
+        println!("{} {}: moyenne={:.1}, {}", comparaison.code, comparaison.chemin, comparaison.moyenne, groupes.join(", "));
+    }
+}
+
+fn arrondir(valeur: f64) -> f64 {
+    (valeur * 100.).round() / 100.
+}
+
+pub fn exporter_statistiques(items: &[StatistiquesItem]) -> Result<()> {
+    let (mut wb, défaut_ref, gras_ref) = nouveau_classeur();
+
+    let mut f_statistiques = Sheet::new_with_name("Statistiques");
+    ["Cours", "Évaluation", "Type", "N", "Moyenne", "Médiane", "Écart-type", "Min", "Max", "Niveaux"]
+        .iter()
+        .enumerate()
+        .for_each(|(i, titre)| f_statistiques.set_styled_value(0, i as u32, *titre, &gras_ref));
+
+    let mut ligne = 1;
+    for item in items {
+        f_statistiques.set_styled_value(ligne, 0, &item.code, &défaut_ref);
+        f_statistiques.set_styled_value(ligne, 1, &item.chemin, &défaut_ref);
+        f_statistiques.set_styled_value(ligne, 2, item.genre, &défaut_ref);
+        f_statistiques.set_styled_value(ligne, 3, item.valeurs.len() as u32, &défaut_ref);
+        if let Some(s) = &item.statistiques {
+            for (i, valeur) in [s.moyenne, s.médiane, s.écart_type, s.min, s.max].iter().enumerate() {
+                f_statistiques.set_styled_value(ligne, 4 + i as u32, arrondir(*valeur), &défaut_ref);
+            }
+        }
+        f_statistiques.set_styled_value(ligne, 9, résumer_niveaux(&item.niveaux), &défaut_ref);
+        ligne += 1;
+    }
+    debug!("feuille=Statistiques rangées={}", ligne - 1);
+
+    let mut f_comparaison = Sheet::new_with_name("Comparaison");
+    ["Cours", "Évaluation", "Groupe", "N", "Moyenne", "Médiane", "Écart-type", "Écart à la moyenne"]
+        .iter()
+        .enumerate()
+        .for_each(|(i, titre)| f_comparaison.set_styled_value(0, i as u32, *titre, &gras_ref));
+
+    let mut ligne = 1;
+    for comparaison in comparer(items) {
+        for groupe in &comparaison.groupes {
+            f_comparaison.set_styled_value(ligne, 0, comparaison.code, &défaut_ref);
+            f_comparaison.set_styled_value(ligne, 1, comparaison.chemin, &défaut_ref);
+            f_comparaison.set_styled_value(ligne, 2, &groupe.code, &défaut_ref);
+            f_comparaison.set_styled_value(ligne, 3, groupe.valeurs.len() as u32, &défaut_ref);
+            if let Some(s) = &groupe.statistiques {
+                f_comparaison.set_styled_value(ligne, 4, arrondir(s.moyenne), &défaut_ref);
+                f_comparaison.set_styled_value(ligne, 5, arrondir(s.médiane), &défaut_ref);
+                f_comparaison.set_styled_value(ligne, 6, arrondir(s.écart_type), &défaut_ref);
+                f_comparaison.set_styled_value(ligne, 7, arrondir(s.moyenne - comparaison.moyenne), &défaut_ref);
+            }
+            ligne += 1;
+        }
+    }
+    debug!("feuille=Comparaison rangées={}", ligne - 1);
+
+    wb.push_sheet(f_statistiques);
+    wb.push_sheet(f_comparaison);
+
+    let date = Local::now().format("%Y-%m-%d_%H-%M-%S");
+    write_ods(&mut wb, format!["statistiques_{}.ods", date])?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bd;
+
+    fn item(code: &str, chemin: &str, valeurs: &[f64]) -> StatistiquesItem {
+        StatistiquesItem {
+            code: code.to_string(),
+            chemin: chemin.to_string(),
+            nom: chemin.to_string(),
+            profondeur: 0,
+            genre: "Évaluation",
+            valeurs: valeurs.to_vec(),
+            statistiques: calculer(valeurs),
+            niveaux: Vec::new()
+        }
+    }
+
+    // valeurs, n, moyenne, médiane, écart-type, min, max
+    type Cas = (&'static [f64], usize, f64, f64, f64, f64, f64);
+
+    #[test]
+    fn statistiques() {
+        let cas: &[Cas] = &[
+            (&[70.], 1, 70., 70., 0., 70., 70.),
+            (&[90., 60., 80., 70.], 4, 75., 75., 125f64.sqrt(), 60., 90.),
+            (&[2., 4., 4., 4., 5., 5., 7., 9.], 8, 5., 4.5, 2., 2., 9.),
+            (&[3., 1., 2.], 3, 2., 2., (2f64 / 3.).sqrt(), 1., 3.)
+        ];
+        for (valeurs, n, moyenne, médiane, écart_type, min, max) in cas {
+            let s = calculer(valeurs).unwrap();
+            assert_eq!((s.n, s.moyenne, s.médiane, s.min, s.max), (*n, *moyenne, *médiane, *min, *max), "{:?}", valeurs);
+            assert!((s.écart_type - écart_type).abs() < 1e-9, "{:?}", valeurs);
+        }
+        assert!(calculer(&[]).is_none());
+    }
+
+    #[test]
+    fn codes() {
+        for (code, base) in &[("MHF4U-01", "MHF4U"), ("MHF4U-02", "MHF4U"), ("MHF4U", "MHF4U"), ("ENG4U-A-1", "ENG4U")] {
+            assert_eq!(code_de_base(code), *base);
+        }
+    }
+
+    #[test]
+    fn comparaisons() {
+        let items = vec![
+            item("MHF4U-01", "Test 1", &[60., 80.]),
+            item("MHF4U-02", "Test 1", &[90.]),
+            item("MHF4U-01", "Examen", &[70.]),
+            item("MHF4U-02", "Examen", &[]),
+            item("MCV4U", "Test 1", &[50.])
+        ];
+
+        let comparaisons = comparer(&items);
+        assert_eq!(comparaisons.len(), 1);
+        let c = &comparaisons[0];
+        assert_eq!((c.code, c.chemin, c.moyenne), ("MHF4U", "Test 1", 230. / 3.));
+        assert_eq!(c.groupes.iter().map(|g| g.code.as_str()).collect::<Vec<_>>(), vec!["MHF4U-01", "MHF4U-02"]);
+    }
+
+    #[test]
+    fn répartition_niveaux() {
+        let conn = bd::ouvrir(None).unwrap();
+        conn.execute_batch("
+            INSERT INTO année_scolaire(id, nom, début, fin) VALUES (100, '2030-2031', '2030-09-01', '2031-06-30');
+            INSERT INTO session(id, id_année, semestre) VALUES (100, 100, 1);
+            INSERT INTO cours(id, code, id_session) VALUES (1, 'MHF4U-01', 100);
+            INSERT INTO élève(id, prénom, nom) VALUES (1, 'Léa', 'Tremblay'), (2, 'Noah', 'Gagnon'), (3, 'Emma', 'Roy');
+            INSERT INTO inscription(id_élève, id_cours) VALUES (1, 1), (2, 1), (3, 1);
+            INSERT INTO échelle(id, nom, précision, min, max) VALUES (1, 'Pourcentage', 0, 0, 100);
+            INSERT INTO évaluation_item(id, nom, id_cours, id_parent, indice, id_échelle) VALUES (1, 'Test 1', 1, NULL, 0, 1);
+            INSERT INTO évaluation_item(id, nom, id_cours, id_parent, indice) VALUES (2, 'A', 1, 1, 0);
+            INSERT INTO évaluation_résultat(id_item, id_élève, résultat) VALUES (2, 1, 45), (2, 2, 70), (2, 3, 79.5);
+        ").unwrap();
+        bd::créer_niveaux(&conn).unwrap();
+
+        let items = calculer_statistiques(&conn, 100, &[]).unwrap();
+        let niveaux: Vec<(&str, usize)> = items[0].niveaux.iter().map(|(nom, n)| (nom.as_str(), *n)).collect();
+        assert_eq!(niveaux, vec![("R", 1), ("1", 0), ("2", 0), ("3", 2), ("4", 0)]);
+    }
+}